@@ -0,0 +1,466 @@
+use std::error::Error;
+use std::io::{self, BufRead};
+use std::process::exit;
+use std::thread;
+use std::time::Duration;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use crate::api::Api;
+use crate::provider::{Provider, RepoSummary, RepoUrls, NEGATIONS};
+
+/// How long to wait for a fork to be ready, as a number of polls and the time between them.
+const FORK_POLLS: u32 = 60;
+const FORK_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+pub fn list_subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("list")
+        .about("Lists repositories, following every page of results.")
+        .arg(
+            Arg::with_name("private")
+                .long("private")
+                .help("Only lists private repos."),
+        )
+        .arg(
+            Arg::with_name("archived")
+                .long("archived")
+                .help("Only lists archived repos."),
+        )
+        .arg(
+            Arg::with_name("name_contains")
+                .long("name_contains")
+                .takes_value(true)
+                .help("Only lists repos whose name contains this text, ignoring case."),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Prints the repos as JSON instead of a table."),
+        )
+}
+
+pub fn show_subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("show")
+        .about("Shows a repo's settings as the flags that would create it, and its clone urls.")
+        .arg(
+            Arg::with_name("repo")
+                .help("The repo to show, as owner/name.")
+                .required(true)
+        ).arg(
+            Arg::with_name("command")
+                .long("command")
+                .help("Only prints the gitpub command that would create the repo with the same settings.")
+                .conflicts_with("json")
+        ).arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Prints the repo as JSON, with its settings keyed by flag name.")
+        )
+}
+
+pub fn fork_subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("fork")
+        .about("Forks a repo. With --set_remote, origin is set to the fork and upstream to the original.")
+        .arg(
+            Arg::with_name("repo")
+                .help("The repo to fork, as owner/name.")
+                .required(true)
+        ).arg(
+            Arg::with_name("no_wait")
+                .long("no_wait")
+                .help("Returns as soon as the fork is requested instead of waiting until it's ready to clone.")
+        )
+}
+
+/// The `archive` or `unarchive` subcommand, which differ only in name.
+pub fn archive_subcommand(name: &'static str) -> App<'static, 'static> {
+    SubCommand::with_name(name)
+        .about(if name == "archive" {
+            "Archives repos, making them read-only."
+        } else {
+            "Unarchives repos."
+        })
+        .arg(
+            Arg::with_name("repos")
+                .help(
+                    "The repos as owner/name. Read from stdin, one per line, when none are given.",
+                )
+                .multiple(true),
+        )
+}
+
+pub fn rename_subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("rename")
+        .about("Renames a repo. Remotes in the current checkout pointing at it are updated.")
+        .arg(
+            Arg::with_name("repo")
+                .help("The repo to rename, as owner/name.")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("to")
+                .long("to")
+                .takes_value(true)
+                .required(true)
+                .help("The new name of the repo."),
+        )
+}
+
+pub fn transfer_subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("transfer")
+        .about("Moves a repo to another owner. Remotes in the current checkout pointing at it are updated.")
+        .arg(
+            Arg::with_name("repo")
+                .help("The repo to transfer, as owner/name.")
+                .required(true)
+        ).arg(
+            Arg::with_name("to")
+                .long("to")
+                .takes_value(true)
+                .required(true)
+                .help("The user, org, group or namespace to move the repo to.")
+        )
+}
+
+pub fn like_arg() -> Arg<'static, 'static> {
+    Arg::with_name("like")
+        .long("like")
+        .takes_value(true)
+        .value_name("owner/repo")
+        .help("Copies the settings of an existing repo on the same provider. Values passed alongside it take precedence, and flags it sets can be turned off with their opposite, like --public or --enable_wiki.")
+}
+
+/// The flags that turn off each of `flags` when they're copied from `--like`.
+pub fn unlike_args(flags: &[&str]) -> Vec<Arg<'static, 'static>> {
+    NEGATIONS
+        .iter()
+        .filter(|(flag, _, _)| flags.contains(flag))
+        .map(|(flag, negation, help)| {
+            Arg::with_name(negation)
+                .long(negation)
+                .requires("like")
+                .conflicts_with(flag)
+                .help(help)
+        })
+        .collect()
+}
+
+/// Looks up the repository the config describes. `None` means the name is still available.
+pub fn existing(config: &dyn Provider, api: &Api) -> Result<Option<Value>, Box<dyn Error>> {
+    let response = api.get(&config.repo_path(api)?)?;
+    match response.status {
+        StatusCode::NOT_FOUND => Ok(None),
+        _ => response.into_json().map(Some),
+    }
+}
+
+/// Reports whether the name is available, exiting with an error if it's taken.
+pub fn check(config: &dyn Provider) -> Result<(), Box<dyn Error>> {
+    match existing(config, &Api::new(config))? {
+        Some(repo) => {
+            eprintln!(
+                "Already exists: {}",
+                config.repo_urls(&repo).web.unwrap_or_default()
+            );
+            exit(17);
+        }
+        None => println!("`{}` is available.", config.slug()?),
+    }
+
+    Ok(())
+}
+
+/// Lists every repo of the owner, following pagination, and prints the ones matching the filters.
+pub fn list(config: &dyn Provider, matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let api = Api::new(config);
+    let mut repos = Vec::new();
+    let mut next = Some(config.list_path());
+    while let Some(path) = next {
+        let response = api.get(&path)?;
+        next = config.next_page(&response, &path);
+        let page = response.into_json()?;
+        repos.extend(
+            config
+                .list_page(&page)
+                .iter()
+                .map(|repo| config.repo_summary(repo)),
+        );
+    }
+
+    let name_contains = matches.value_of("name_contains").map(str::to_lowercase);
+    repos.retain(|repo| {
+        (!matches.is_present("private") || repo.private)
+            && (!matches.is_present("archived") || repo.archived)
+            && name_contains
+                .as_ref()
+                .is_none_or(|n| repo.name.to_lowercase().contains(n))
+    });
+
+    if matches.is_present("json") {
+        println!("{}", serde_json::to_string_pretty(&repos)?);
+    } else {
+        print_table(&repos);
+    }
+
+    Ok(())
+}
+
+fn print_table(repos: &[RepoSummary]) {
+    let rows = repos
+        .iter()
+        .map(|repo| {
+            [
+                repo.name.as_str(),
+                if repo.private { "private" } else { "public" },
+                if repo.archived { "archived" } else { "" },
+                repo.url.as_str(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let header = ["NAME", "VISIBILITY", "ARCHIVED", "URL"];
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        let line = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    }
+}
+
+/// Fetches a repository given as `owner/name`, exiting if it doesn't exist.
+pub fn fetch(config: &dyn Provider, full_name: &str) -> Result<Value, Box<dyn Error>> {
+    let response = Api::new(config).get(&config.full_repo_path(full_name))?;
+    if response.status == StatusCode::NOT_FOUND {
+        eprintln!("`{}` doesn't exist, or your token can't see it.", full_name);
+        exit(44);
+    }
+    response.into_json()
+}
+
+/// Forks a repo given as `owner/name` and waits until the fork is ready, returning the original and the fork.
+pub fn fork(config: &dyn Provider, matches: &ArgMatches) -> Result<(Value, Value), Box<dyn Error>> {
+    let full_name = matches
+        .value_of("repo")
+        .expect("This property is required. This error should be unreachable.");
+    let upstream = fetch(config, full_name)?;
+    let api = Api::new(config);
+    let fork = api
+        .request(
+            Method::POST,
+            &config.fork_path(full_name),
+            Some(&config.fork_payload()),
+        )?
+        .into_json()?;
+    let url = config.repo_urls(&fork).web.unwrap_or_default();
+    println!("Forked {} to {}", full_name, url);
+
+    if !matches.is_present("no_wait") && !config.fork_ready(&api, &fork)? {
+        println!("Waiting for the fork to be ready...");
+        let mut polls = 1;
+        while !config.fork_ready(&api, &fork)? {
+            if polls == FORK_POLLS {
+                return Err(format!(
+                    "The fork still wasn't ready after {} seconds, it may finish later: {}",
+                    FORK_POLLS * FORK_POLL_INTERVAL.as_secs() as u32,
+                    url
+                )
+                .into());
+            }
+            thread::sleep(FORK_POLL_INTERVAL);
+            polls += 1;
+        }
+    }
+
+    Ok((upstream, fork))
+}
+
+/// Archives or unarchives every repo given, or every repo on stdin, carrying on past failures.
+pub fn archive(
+    config: &dyn Provider,
+    matches: &ArgMatches,
+    archived: bool,
+) -> Result<(), Box<dyn Error>> {
+    let repos = match matches.values_of("repos") {
+        Some(repos) => repos.map(str::to_string).collect(),
+        None => repos_from_stdin()?,
+    };
+    let api = Api::new(config);
+    let mut failed = 0;
+    for full_name in &repos {
+        let response = config
+            .archive_request(full_name, archived)
+            .and_then(|(method, path, body)| api.request(method, &path, body.as_ref()))
+            .and_then(|response| response.into_json());
+        match response {
+            Ok(_) if archived => println!("Archived {}", full_name),
+            Ok(_) => println!("Unarchived {}", full_name),
+            Err(e) => {
+                eprintln!("Couldn't update {}: {}", full_name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        eprintln!("{} of {} repos couldn't be updated.", failed, repos.len());
+        exit(2);
+    }
+
+    Ok(())
+}
+
+/// Reads repos as `owner/name`, one per line, skipping blank lines and `#` comments.
+fn repos_from_stdin() -> Result<Vec<String>, Box<dyn Error>> {
+    let mut repos = Vec::new();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            repos.push(line.to_string());
+        }
+    }
+
+    Ok(repos)
+}
+
+/// Renames a repo, or transfers it to another owner, returning its old urls and its old and new `owner/name`.
+pub fn move_repo(
+    config: &dyn Provider,
+    matches: &ArgMatches,
+    transfer: bool,
+) -> Result<(RepoUrls, Option<RepoUrls>), Box<dyn Error>> {
+    let full_name = matches
+        .value_of("repo")
+        .expect("This property is required. This error should be unreachable.");
+    let to = matches
+        .value_of("to")
+        .expect("This property is required. This error should be unreachable.");
+    let old_urls = config.repo_urls(&fetch(config, full_name)?);
+
+    let (owner, name) = full_name.rsplit_once('/').unwrap_or(("", full_name));
+    let ((method, path, body), expected) = if transfer {
+        (
+            config.transfer_request(full_name, to)?,
+            format!("{}/{}", to, name),
+        )
+    } else {
+        (
+            config.rename_request(full_name, to),
+            format!("{}/{}", owner, to),
+        )
+    };
+    let moved = Api::new(config)
+        .request(method, &path, body.as_ref())?
+        .into_json()?;
+
+    // Prefer the name the provider reports, it knows the slug and the path of a namespace given by id.
+    // Github transfers in the background though, and responds with the old name, so the moved repo
+    // is looked up for its urls.
+    let reported = config.repo_summary(&moved).name;
+    let (new_name, new_urls) = if reported.is_empty() || reported == full_name {
+        let urls = fetch(config, &expected).map(|repo| config.repo_urls(&repo));
+        (expected, urls)
+    } else {
+        (reported, Ok(config.repo_urls(&moved)))
+    };
+    if transfer {
+        println!("Transferred {} to {}", full_name, new_name);
+    } else {
+        println!("Renamed {} to {}", full_name, new_name);
+    }
+
+    match new_urls {
+        Ok(new_urls) => Ok((old_urls, Some(new_urls))),
+        Err(e) => {
+            eprintln!(
+                "Couldn't look up the urls of {}, so no remotes were updated: {}",
+                new_name, e
+            );
+            Ok((old_urls, None))
+        }
+    }
+}
+
+/// Shows a repo's settings in terms of the flags that would create it, and the command to do so.
+pub fn show(
+    config: &dyn Provider,
+    provider: &str,
+    matches: &ArgMatches,
+) -> Result<(), Box<dyn Error>> {
+    let full_name = matches
+        .value_of("repo")
+        .expect("This property is required. This error should be unreachable.");
+    let repo = fetch(config, full_name)?;
+
+    let summary = config.repo_summary(&repo);
+    let urls = config.repo_urls(&repo);
+    let settings = config.settings(&repo);
+    let command = std::iter::once(format!("gitpub {}", provider))
+        .chain(settings.args().iter().map(|arg| shell_quote(arg)))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if matches.is_present("command") {
+        println!("{}", command);
+    } else if matches.is_present("json") {
+        let json = json!({
+            "name": summary.name,
+            "private": summary.private,
+            "archived": summary.archived,
+            "default_branch": summary.default_branch,
+            "urls": { "web": urls.web, "https": urls.https, "ssh": urls.ssh },
+            "settings": settings.to_json(),
+            "command": command,
+        });
+        println!("{}", serde_json::to_string_pretty(&json)?);
+    } else {
+        println!("{}", summary.name);
+        let rows = [
+            (
+                "Visibility",
+                Some(if summary.private { "private" } else { "public" }),
+            ),
+            ("Archived", Some("yes").filter(|_| summary.archived)),
+            (
+                "Description",
+                Some(summary.description.as_str()).filter(|d| !d.is_empty()),
+            ),
+            ("Default branch", summary.default_branch.as_deref()),
+            ("Web", urls.web.as_deref()),
+            ("Clone", urls.https.as_deref()),
+            ("SSH", urls.ssh.as_deref()),
+        ];
+        for (label, value) in rows.iter() {
+            if let Some(value) = value {
+                println!("  {:15} {}", format!("{}:", label), value);
+            }
+        }
+        println!();
+        println!("Create a repo with the same settings:");
+        println!("  {}", command);
+    }
+
+    Ok(())
+}
+
+/// Quotes an arg for a POSIX shell if it has anything besides letters, digits and `-_./:@=`.
+pub fn shell_quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./:@=".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
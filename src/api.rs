@@ -0,0 +1,134 @@
+use crate::provider::Provider;
+use reqwest::header::HeaderMap;
+use reqwest::multipart::Form;
+use reqwest::{Method, RequestBuilder, StatusCode, Url};
+use serde_json::Value;
+use std::error::Error;
+
+/// A blocking client for a provider's api, authenticated the same way as the create request.
+pub struct Api {
+    client: reqwest::Client,
+    base: String,
+    auth_header: String,
+    token: String,
+}
+
+/// A response with its body parsed as JSON. Empty or non-JSON bodies are `Value::Null`.
+pub struct Response {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Value,
+}
+
+impl Response {
+    pub fn is_success(&self) -> bool {
+        self.status.is_success()
+    }
+
+    /// The `rel="next"` url from the `Link` header used for pagination.
+    pub fn next_link(&self) -> Option<String> {
+        let link = self.headers.get("link")?.to_str().ok()?;
+        link.split(',').find_map(|part| {
+            let mut sections = part.split(';');
+            let url = sections.next()?.trim();
+            if sections.any(|s| s.trim() == "rel=\"next\"") {
+                Some(
+                    url.trim_start_matches('<')
+                        .trim_end_matches('>')
+                        .to_string(),
+                )
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the body, or the provider's error message if the request failed.
+    pub fn into_json(self) -> Result<Value, Box<dyn Error>> {
+        if self.is_success() {
+            Ok(self.body)
+        } else {
+            Err(self.error_message().into())
+        }
+    }
+
+    /// The provider's error message if it sent one, otherwise the status.
+    pub fn error_message(&self) -> String {
+        let message = self.body["message"]
+            .as_str()
+            .or_else(|| self.body["error"]["message"].as_str())
+            .or_else(|| self.body["error"].as_str());
+        match message {
+            Some(message) => format!("{} ({})", message, self.status),
+            None => self.status.to_string(),
+        }
+    }
+}
+
+impl Api {
+    pub fn new(config: &dyn Provider) -> Api {
+        Api {
+            client: reqwest::Client::new(),
+            base: config.api_url().trim_end_matches('/').to_string(),
+            auth_header: config.auth_header(),
+            token: config.token(),
+        }
+    }
+
+    pub fn get(&self, path: &str) -> Result<Response, Box<dyn Error>> {
+        self.request(Method::GET, path, None)
+    }
+
+    /// Sends a request to a path relative to the api root, or to an absolute url such as a pagination link.
+    pub fn request(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<Response, Box<dyn Error>> {
+        let mut request = self.builder(method, path)?;
+        if let Some(body) = body {
+            request = request
+                .header("Content-Type", "application/json")
+                .body(body.to_string());
+        }
+        Api::send(request)
+    }
+
+    /// Posts a multipart form, for apis that take file uploads.
+    pub fn post_form(&self, path: &str, form: Form) -> Result<Response, Box<dyn Error>> {
+        Api::send(self.builder(Method::POST, path)?.multipart(form))
+    }
+
+    /// Absolute urls only get the token when they're on the api's own scheme and host, so a `Link`
+    /// header pointing elsewhere can't collect it.
+    fn builder(&self, method: Method, path: &str) -> Result<RequestBuilder, Box<dyn Error>> {
+        let url = if path.starts_with("http://") || path.starts_with("https://") {
+            let url = Url::parse(path)?;
+            if url.origin() != Url::parse(&self.base)?.origin() {
+                return Err(format!(
+                    "Refusing to send the token to `{}`, which isn't on the api at {}.",
+                    path, self.base
+                )
+                .into());
+            }
+            url.to_string()
+        } else {
+            format!("{}{}", self.base, path)
+        };
+        Ok(self
+            .client
+            .request(method, &url)
+            .header(self.auth_header.as_bytes(), self.token.as_str()))
+    }
+
+    fn send(request: RequestBuilder) -> Result<Response, Box<dyn Error>> {
+        let mut response = request.send()?;
+        let text = response.text()?;
+        Ok(Response {
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: serde_json::from_str(&text).unwrap_or(Value::Null),
+        })
+    }
+}
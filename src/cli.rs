@@ -1,8 +1,11 @@
+use crate::hook;
+use crate::multi;
+use crate::project::Project;
 use crate::provider::{
     bitbucket::{self, BitbucketArgs},
     github::{self, GithubArgs},
     gitlab::{self, GitlabArgs},
-    Provider,
+    Provider, Settings,
 };
 use clap::{App, AppSettings, Arg, ArgMatches};
 
@@ -15,17 +18,24 @@ pub enum Gitpo<'a> {
 
 impl<'a> Gitpo<'a> {
     /// from_matches constructs a subcommand config from the selected subcommand.
-    pub fn from_matches(matches: &'a ArgMatches) -> Gitpo<'a> {
-        match matches.subcommand_name() {
-            Some("github") => Gitpo::Github(github::from_matches(
-                &matches.subcommand_matches("github").unwrap(),
-            )),
-            Some("gitlab") => Gitpo::Gitlab(gitlab::from_matches(
-                &matches.subcommand_matches("gitlab").unwrap(),
-            )),
-            Some("bitbucket") => Gitpo::BitBucket(bitbucket::from_matches(
-                &matches.subcommand_matches("bitbucket").unwrap(),
-            )),
+    ///
+    /// When an action like `check` is selected, its matches are used since it takes the provider's options itself.
+    /// `like` holds the settings of the repo passed to `--like`, used where the command line doesn't say otherwise.
+    pub fn from_matches(
+        matches: &'a ArgMatches<'a>,
+        project: &'a Project,
+        like: &'a Settings,
+    ) -> Gitpo<'a> {
+        match matches.subcommand() {
+            ("github", Some(m)) => {
+                Gitpo::Github(github::from_matches(action_matches(m), project, like))
+            }
+            ("gitlab", Some(m)) => {
+                Gitpo::Gitlab(gitlab::from_matches(action_matches(m), project, like))
+            }
+            ("bitbucket", Some(m)) => {
+                Gitpo::BitBucket(bitbucket::from_matches(action_matches(m), project, like))
+            }
             _ => unreachable!(),
         }
     }
@@ -35,13 +45,30 @@ impl<'a> Gitpo<'a> {
     /// Returns self as a reference to a Provider
     pub fn as_provider(&self) -> &dyn Provider {
         match self {
-            Gitpo::Github(x) => x as &Provider,
-            Gitpo::Gitlab(x) => x as &Provider,
-            Gitpo::BitBucket(x) => x as &Provider,
+            Gitpo::Github(x) => x as &dyn Provider,
+            Gitpo::Gitlab(x) => x as &dyn Provider,
+            Gitpo::BitBucket(x) => x as &dyn Provider,
         }
     }
 }
 
+/// The action selected under the provider subcommand, like `check`, and its matches. `None` means the repo is being created.
+pub fn action<'a>(matches: &'a ArgMatches) -> Option<(&'a str, &'a ArgMatches<'a>)> {
+    match matches.subcommand().1?.subcommand() {
+        (name, Some(m)) => Some((name, m)),
+        _ => None,
+    }
+}
+
+/// The repo passed to `--like` when creating, as `owner/name`.
+pub fn like<'a>(matches: &'a ArgMatches) -> Option<&'a str> {
+    matches.subcommand().1?.value_of("like")
+}
+
+fn action_matches<'a>(matches: &'a ArgMatches<'a>) -> &'a ArgMatches<'a> {
+    matches.subcommand().1.unwrap_or(matches)
+}
+
 /// ## Returns highest-level clap app.
 pub fn get_app() -> App<'static, 'static> {
     App::new("Git Publish")
@@ -54,6 +81,7 @@ pub fn get_app() -> App<'static, 'static> {
         .subcommand(github::subcommand())
         .subcommand(gitlab::subcommand())
         .subcommand(bitbucket::subcommand())
+        .subcommand(multi::subcommand())
         .arg(
             Arg::with_name("endpoint")
                 .long("endpoint")
@@ -65,16 +93,42 @@ pub fn get_app() -> App<'static, 'static> {
             Arg::with_name("set_remote")
                 .long("set_remote")
                 .help("Sets the remote of the local dir after successful creation."),
+        ).arg(
+            Arg::with_name("replace_remote")
+                .long("replace_remote")
+                .help("Replaces the url of the remote if it already exists instead of refusing to set it.")
+                .requires("set_remote")
+                .conflicts_with("add_push_url"),
+        ).arg(
+            Arg::with_name("add_push_url")
+                .long("add_push_url")
+                .help("Adds the new repo as an extra push url on an existing remote, useful for mirroring.")
+                .requires("set_remote")
+                .conflicts_with("replace_remote"),
         ).arg(
             Arg::with_name("remote_name")
                 .long("remote_name")
                 .help("Designates a custom name for setting remote. Defaults to origin.")
                 .default_value("origin")
                 .hide_default_value(true),
+        ).arg(
+            Arg::with_name("use_existing")
+                .long("use_existing")
+                .help("If the repo already exists, sets it as the remote instead of failing.")
+                .requires("set_remote"),
+        ).arg(
+            Arg::with_name("ignore_manifest")
+                .long("ignore_manifest")
+                .help("Don't fill in the description, homepage and tags from Cargo.toml, package.json or pyproject.toml."),
         ).arg(
             Arg::with_name("ssh_remote_format")
                 .long("ssh_remote_format")
                 .help("Attempts to convert the git remote url into ssh format. If it fails (the provider doesn't support ssh format), the remote isn't set.")
                 .conflicts_with("endpoint"),
+        ).args(&hook::args())
+        .arg(
+            Arg::with_name("interactive")
+                .long("interactive")
+                .help("Asks for the provider, name, visibility and other common options one at a time, and shows the equivalent command before creating the repo. Also starts when gitpub is run without arguments in a terminal."),
         )
 }
@@ -1,22 +1,167 @@
 use std::env::current_dir;
 use std::process::Command;
 
+/// How a remote is written to the local repository after creation.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RemoteMode {
+    /// `git remote add`, refusing if the remote already exists.
+    Add,
+    /// `git remote set-url`, adding the remote if it doesn't exist yet.
+    Replace,
+    /// `git remote set-url --add --push`, requires an existing remote.
+    AddPushUrl,
+}
+
+impl RemoteMode {
+    pub fn from_matches(matches: &clap::ArgMatches) -> RemoteMode {
+        if matches.is_present("replace_remote") {
+            RemoteMode::Replace
+        } else if matches.is_present("add_push_url") {
+            RemoteMode::AddPushUrl
+        } else {
+            RemoteMode::Add
+        }
+    }
+}
+
 pub fn add_remote(name: &str, url: &str) -> bool {
+    if can_create_remote(name) && in_repository() {
+        return git(&["remote", "add", name, url]);
+    }
+
+    false
+}
+
+pub fn replace_remote(name: &str, url: &str) -> bool {
+    if !in_repository() {
+        return false;
+    }
+
     if can_create_remote(name) {
-        if let Ok(dir) = current_dir() {
-            if dir.ancestors().any(|p| p.join(".git").exists()) {
-                return Command::new("git")
-                    .arg("remote")
-                    .arg("add")
-                    .arg(name)
-                    .arg(url)
-                    .output()
-                    .is_ok();
-            }
+        git(&["remote", "add", name, url])
+    } else {
+        git(&["remote", "set-url", name, url])
+    }
+}
+
+pub fn add_push_url(name: &str, url: &str) -> bool {
+    if !in_repository() || can_create_remote(name) {
+        return false;
+    }
+
+    // The first explicit push url replaces the implicit one, so keep pushing to the fetch url too.
+    if !has_push_url(name) {
+        match remote_url(name) {
+            Some(fetch) if git(&["remote", "set-url", "--add", "--push", name, &fetch]) => {}
+            _ => return false,
         }
     }
 
-    false
+    git(&["remote", "set-url", "--add", "--push", name, url])
+}
+
+/// The names of the local repository's remotes with their fetch urls.
+pub fn remotes() -> Vec<(String, String)> {
+    if !in_repository() {
+        return Vec::new();
+    }
+
+    Command::new("git")
+        .arg("remote")
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|name| remote_url(name).map(|url| (name.to_string(), url)))
+        .collect()
+}
+
+/// A value from git's config, like `user.name`.
+pub fn config(key: &str) -> Option<String> {
+    Command::new("git")
+        .args(["config", "--get", key])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Every value of a key that can be set more than once in git's config, in the order git reads them.
+pub fn config_all(key: &str) -> Vec<String> {
+    Command::new("git")
+        .args(["config", "--get-all", key])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .unwrap_or_default()
+        .lines()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+/// Sets the remote using the given mode.
+pub fn set_remote(mode: RemoteMode, name: &str, url: &str) -> bool {
+    match mode {
+        RemoteMode::Add => add_remote(name, url),
+        RemoteMode::Replace => replace_remote(name, url),
+        RemoteMode::AddPushUrl => add_push_url(name, url),
+    }
+}
+
+/// Checks that the remote can be set with the given mode without touching anything.
+///
+/// This is run before the provider is contacted so a bad remote state never leaves an orphaned repository behind.
+pub fn check_remote(mode: RemoteMode, name: &str) -> Result<(), String> {
+    if !in_repository() {
+        return Err("The current directory isn't inside a git repository.".to_string());
+    }
+
+    match (mode, can_create_remote(name)) {
+        (RemoteMode::Add, false) => Err(format!(
+            "The remote `{}` already exists. Use --replace_remote or --add_push_url to update it.",
+            name
+        )),
+        (RemoteMode::AddPushUrl, true) => Err(format!(
+            "The remote `{}` doesn't exist, so a push url can't be added to it.",
+            name
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub fn in_repository() -> bool {
+    current_dir()
+        .map(|dir| dir.ancestors().any(|p| p.join(".git").exists()))
+        .unwrap_or(false)
+}
+
+fn remote_url(name: &str) -> Option<String> {
+    Command::new("git")
+        .arg("remote")
+        .arg("get-url")
+        .arg(name)
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|url| url.trim().to_string())
+}
+
+fn has_push_url(name: &str) -> bool {
+    git(&["config", "--get-all", &format!("remote.{}.pushurl", name)])
+}
+
+fn git(args: &[&str]) -> bool {
+    Command::new("git")
+        .args(args)
+        .output()
+        .map(|out| out.status.success())
+        .unwrap_or(false)
 }
 
 fn can_create_remote(name: &str) -> bool {
@@ -27,7 +172,7 @@ fn can_create_remote(name: &str) -> bool {
         .output()
     {
         if let Ok(err) = String::from_utf8(out.stderr) {
-            return err.starts_with("fatal"); //If it starts with fatal, that means the remote doesn't exist and we can create one
+            return err.starts_with("fatal") || err.starts_with("error"); //If it starts with fatal, that means the remote doesn't exist and we can create one
         }
     }
 
@@ -0,0 +1,143 @@
+//! User scripts run before a repo is created and after, to plug in checks like naming policies
+//! or follow-ups like opening a ticket.
+use crate::git;
+use crate::provider::Provider;
+use clap::{Arg, ArgMatches};
+use serde_json::Value;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// The hooks set in git's config as `gitpub.preHook` and `gitpub.postHook`, followed by the ones passed
+/// with `--pre_hook` and `--post_hook`.
+pub struct Hooks {
+    pre: Vec<String>,
+    post: Vec<String>,
+}
+
+impl Hooks {
+    pub fn from_matches(matches: &ArgMatches) -> Hooks {
+        let hooks = |key, arg| {
+            let mut hooks = git::config_all(key);
+            hooks.extend(
+                matches
+                    .values_of(arg)
+                    .into_iter()
+                    .flatten()
+                    .map(String::from),
+            );
+            hooks
+        };
+        Hooks {
+            pre: hooks("gitpub.prehook", "pre_hook"),
+            post: hooks("gitpub.posthook", "post_hook"),
+        }
+    }
+
+    /// Runs the pre-hooks in order, stopping at the first that fails.
+    pub fn run_pre(&self, env: &[(&str, String)]) -> Result<(), String> {
+        for hook in &self.pre {
+            match run(hook, env, None) {
+                Ok(true) => {}
+                Ok(false) => {
+                    return Err(format!(
+                        "The pre-hook `{}` failed, so nothing was created.",
+                        hook
+                    ))
+                }
+                Err(e) => return Err(format!("Couldn't run the pre-hook `{}`: {}", hook, e)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs every post-hook with the created repo on stdin, warning about each one that fails.
+    /// Returns whether they all succeeded.
+    pub fn run_post(&self, env: &[(&str, String)], repo: &Value) -> bool {
+        let mut ok = true;
+        for hook in &self.post {
+            match run(hook, env, Some(repo)) {
+                Ok(true) => {}
+                Ok(false) => {
+                    eprintln!("The post-hook `{}` failed.", hook);
+                    ok = false;
+                }
+                Err(e) => {
+                    eprintln!("Couldn't run the post-hook `{}`: {}", hook, e);
+                    ok = false;
+                }
+            }
+        }
+        ok
+    }
+}
+
+/// The environment describing the repo to a hook. The urls are only known once the repo is created,
+/// and the remote is only named when `--set_remote` is passed.
+pub fn env(
+    provider: &str,
+    config: &dyn Provider,
+    repo: &Value,
+    remote: Option<&str>,
+) -> Vec<(&'static str, String)> {
+    let urls = config.repo_urls(repo);
+    vec![
+        ("GITPUB_PROVIDER", provider.to_string()),
+        (
+            "GITPUB_NAME",
+            config.slug().unwrap_or_else(|_| config.name()),
+        ),
+        ("GITPUB_WEB_URL", urls.web.unwrap_or_default()),
+        ("GITPUB_SSH_URL", urls.ssh.unwrap_or_default()),
+        ("GITPUB_REMOTE", remote.unwrap_or_default().to_string()),
+    ]
+}
+
+/// Runs a hook through the shell, passing its output through. Returns whether it exited successfully.
+fn run(hook: &str, env: &[(&str, String)], stdin: Option<&Value>) -> std::io::Result<bool> {
+    let mut command = shell(hook);
+    command.envs(env.iter().map(|(name, value)| (name, value)));
+    command.stdin(if stdin.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    });
+    let mut child = command.spawn()?;
+    if let (Some(repo), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        // Hooks that don't read stdin close it early, which isn't their failure.
+        let _ = pipe.write_all(repo.to_string().as_bytes());
+    }
+    Ok(child.wait()?.success())
+}
+
+#[cfg(unix)]
+fn shell(hook: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.args(["-c", hook]);
+    command
+}
+
+#[cfg(not(unix))]
+fn shell(hook: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command.args(["/C", hook]);
+    command
+}
+
+pub fn args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("pre_hook")
+            .long("pre_hook")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("command")
+            .help("Runs a shell command before the repo is created, which stops it being created if it fails. GITPUB_PROVIDER, GITPUB_NAME and GITPUB_REMOTE describe the repo. Also read from `git config gitpub.preHook`."),
+        Arg::with_name("post_hook")
+            .long("post_hook")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("command")
+            .help("Runs a shell command after the repo is created and the remote is set, with the created repo as json on stdin and GITPUB_WEB_URL and GITPUB_SSH_URL set too. Also read from `git config gitpub.postHook`."),
+    ]
+}
@@ -1,30 +1,423 @@
 // #![allow(clippy::nonminimal_bool)]
 use std::process::exit;
 
+mod action;
+mod api;
 mod cli;
 mod git;
+mod hook;
+mod multi;
+mod project;
 mod provider;
+mod wizard;
 
+use api::Api;
 use cli::Gitpo;
+use hook::Hooks;
 use reqwest::StatusCode;
+use serde_json::Value;
 
-use provider::Provider;
+use project::Project;
+use provider::{Provider, Settings};
 
-use git::add_remote;
+use git::{check_remote, set_remote, RemoteMode};
 
 pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let matches = cli::get_app().get_matches();
-    let config = Gitpo::from_matches(&matches);
+    let matches = match wizard::args() {
+        Some(args) => cli::get_app().get_matches_from(args),
+        None => cli::get_app().get_matches(),
+    };
+    if let Some(multi_matches) = matches.subcommand_matches("multi") {
+        return run_multi(&matches, multi_matches);
+    }
+
+    let project = Project::detect(!matches.is_present("ignore_manifest"));
+    let like = like_settings(&matches, &project)?;
+    let config = Gitpo::from_matches(&matches, &project, &like);
+    let config = config.as_provider();
+    let remote_mode = RemoteMode::from_matches(&matches);
+    let remote_name = matches
+        .value_of("remote_name")
+        .expect("This should default to origin, so something is wrong.");
+
+    match cli::action(&matches) {
+        Some(("list", action_matches)) => return action::list(config, action_matches),
+        Some(("archive", action_matches)) => return action::archive(config, action_matches, true),
+        Some(("unarchive", action_matches)) => {
+            return action::archive(config, action_matches, false)
+        }
+        Some(("rename", action_matches)) => return move_repo(config, action_matches, false),
+        Some(("transfer", action_matches)) => return move_repo(config, action_matches, true),
+        Some(("fork", action_matches)) => {
+            return fork(config, &matches, action_matches);
+        }
+        Some(("show", action_matches)) => {
+            let provider = matches.subcommand_name().unwrap_or_default();
+            return action::show(config, provider, action_matches);
+        }
+        Some(("check", _)) => {
+            check_name(config);
+            return action::check(config);
+        }
+        _ => check_name(config),
+    }
+
+    if matches.is_present("set_remote") {
+        preflight_remote(remote_mode, remote_name);
+    }
+    preflight_options(config, &matches);
+
+    let hooks = Hooks::from_matches(&matches);
+    let provider = matches.subcommand_name().unwrap_or_default();
+    let hook_remote = Some(remote_name).filter(|_| matches.is_present("set_remote"));
+    let mut steps_ok = true;
+    let mut created = None;
+    let remote_url = match find_existing(config, &matches) {
+        Some(repo) => existing_remote_url(config, &repo, &matches),
+        None => {
+            run_pre_hooks(&hooks, provider, config, hook_remote);
+            let request = configure_request(config, &matches);
+            let result = request.send()?;
+            let (remote_url, repo) = handle_result(result, config, &matches);
+            steps_ok = post_create(config, &repo);
+            created = Some(repo);
+            remote_url
+        }
+    };
+    if matches.is_present("set_remote") {
+        write_remote(remote_mode, remote_name, &remote_url);
+    }
+    if let Some(repo) = created {
+        steps_ok &= hooks.run_post(&hook::env(provider, config, &repo, hook_remote), &repo);
+    }
+    if !steps_ok {
+        exit(2);
+    }
+
+    Ok(())
+}
+
+/// Creates the repo on every provider passed to the `multi` subcommand.
+///
+/// The first provider becomes the remote's fetch url and the others are added as push urls,
+/// unless `--remote_per_provider` asks for a remote named after each provider.
+fn run_multi(
+    matches: &clap::ArgMatches,
+    multi_matches: &clap::ArgMatches,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if matches.is_present("endpoint") {
+        eprintln!("Can't use --endpoint with multi, each provider needs its own endpoint.");
+        exit(22);
+    }
+
+    let providers = multi::provider_matches(multi_matches).unwrap_or_else(|e| e.exit());
+    let per_provider = multi_matches.is_present("remote_per_provider");
+    let remote_mode = RemoteMode::from_matches(matches);
+    let remote_name = matches
+        .value_of("remote_name")
+        .expect("This should default to origin, so something is wrong.");
+
+    if matches.is_present("set_remote") {
+        if per_provider {
+            for (provider, _) in &providers {
+                preflight_remote(remote_mode, provider);
+            }
+        } else {
+            preflight_remote(remote_mode, remote_name);
+        }
+    }
+
+    let project = Project::detect(!matches.is_present("ignore_manifest"));
+    let like = Settings::default();
+    let configs = providers
+        .iter()
+        .map(|(_, provider_matches)| Gitpo::from_matches(provider_matches, &project, &like))
+        .collect::<Vec<_>>();
+    for config in &configs {
+        check_name(config.as_provider());
+        preflight_options(config.as_provider(), matches);
+    }
+    let existing = configs
+        .iter()
+        .map(|config| find_existing(config.as_provider(), matches))
+        .collect::<Vec<_>>();
+
+    let hooks = Hooks::from_matches(matches);
+    let hook_remote = |provider| {
+        Some(if per_provider { provider } else { remote_name })
+            .filter(|_| matches.is_present("set_remote"))
+    };
+    for (i, ((provider, _), config)) in providers.iter().zip(&configs).enumerate() {
+        if existing[i].is_none() {
+            run_pre_hooks(
+                &hooks,
+                provider,
+                config.as_provider(),
+                hook_remote(provider),
+            );
+        }
+    }
+
+    let mut steps_ok = true;
+    let mut created_urls = vec![];
+    for (i, ((provider, provider_matches), config)) in providers.iter().zip(&configs).enumerate() {
+        let config = config.as_provider();
+        let mut created = None;
+        let remote_url = match &existing[i] {
+            Some(repo) => remote_url(config, repo, matches).unwrap_or_else(|| {
+                abort_multi(
+                    &created_urls,
+                    "The provider didn't respond with a url to set as the remote.",
+                    42,
+                )
+            }),
+            None => {
+                let request = configure_request(config, provider_matches);
+                let result = request
+                    .send()
+                    .unwrap_or_else(|e| abort_multi(&created_urls, &e.to_string(), 1));
+                let (remote_url, repo) = creation_result(result, config, matches)
+                    .unwrap_or_else(|(message, code)| abort_multi(&created_urls, &message, code));
+                created_urls.push(config.repo_urls(&repo).web.unwrap_or(remote_url.clone()));
+                steps_ok &= post_create(config, &repo);
+                created = Some(repo);
+                remote_url
+            }
+        };
+        if matches.is_present("set_remote") {
+            let (mode, name) = if per_provider {
+                (remote_mode, *provider)
+            } else if i == 0 {
+                (remote_mode, remote_name)
+            } else {
+                (RemoteMode::AddPushUrl, remote_name)
+            };
+            if !set_remote(mode, name, &remote_url) {
+                abort_multi(&created_urls, "Failed to set remote.", 404);
+            }
+        }
+        if let Some(repo) = created {
+            let env = hook::env(provider, config, &repo, hook_remote(provider));
+            steps_ok &= hooks.run_post(&env, &repo);
+        }
+    }
+    if !steps_ok {
+        exit(2);
+    }
+
+    Ok(())
+}
+
+/// Exits after a provider fails, listing the repos the providers before it already created so
+/// they can be cleaned up.
+fn abort_multi(created_urls: &[String], message: &str, code: i32) -> ! {
+    eprintln!("{}", message);
+    if !created_urls.is_empty() {
+        eprintln!("These repos were created before the failure:");
+        for url in created_urls {
+            eprintln!("    {}", url);
+        }
+    }
+    exit(code);
+}
+
+/// The settings of the repo passed to `--like`, fetched with the same credentials the repo is created with.
+fn like_settings(
+    matches: &clap::ArgMatches,
+    project: &Project,
+) -> Result<Settings, Box<dyn std::error::Error>> {
+    let full_name = match cli::like(matches) {
+        Some(full_name) => full_name,
+        None => return Ok(Settings::default()),
+    };
+    let none = Settings::default();
+    let config = Gitpo::from_matches(matches, project, &none);
     let config = config.as_provider();
+    let repo = action::fetch(config, full_name)?;
+    println!("Using the settings of `{}`.", full_name);
+
+    Ok(config.settings(&repo))
+}
+
+/// Forks a repo, then points the remote at the fork and `upstream` at the original.
+fn fork(
+    config: &dyn Provider,
+    matches: &clap::ArgMatches,
+    fork_matches: &clap::ArgMatches,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let remote_mode = RemoteMode::from_matches(matches);
+    let remote_name = matches
+        .value_of("remote_name")
+        .expect("This should default to origin, so something is wrong.");
+    if matches.is_present("set_remote") {
+        preflight_remote(remote_mode, remote_name);
+        preflight_remote(remote_mode, "upstream");
+    }
+
+    let (upstream, fork) = action::fork(config, fork_matches)?;
+    if matches.is_present("set_remote") {
+        write_remote(
+            remote_mode,
+            remote_name,
+            &existing_remote_url(config, &fork, matches),
+        );
+        write_remote(
+            remote_mode,
+            "upstream",
+            &existing_remote_url(config, &upstream, matches),
+        );
+    }
+
+    Ok(())
+}
 
-    let request = configure_request(config, &matches);
-    let result = request.send()?;
+/// Renames or transfers a repo, then points any remote of the current checkout that used its old url at the new one.
+fn move_repo(
+    config: &dyn Provider,
+    move_matches: &clap::ArgMatches,
+    transfer: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (old_urls, new_urls) = action::move_repo(config, move_matches, transfer)?;
+    let new_urls = match new_urls {
+        Some(new_urls) => new_urls,
+        None => return Ok(()),
+    };
 
-    handle_result(result, config, &matches);
+    // Each kind of old url is replaced by the same kind of new one, with web urls cloned over https.
+    let normalize = |url: &str| {
+        url.trim_end_matches('/')
+            .trim_end_matches(".git")
+            .to_lowercase()
+    };
+    let replacements = [
+        (old_urls.web, new_urls.https.clone().or(new_urls.web)),
+        (old_urls.https, new_urls.https),
+        (old_urls.ssh, new_urls.ssh),
+    ];
+    for (remote, url) in git::remotes() {
+        let replacement = replacements
+            .iter()
+            .find(|(old, _)| old.as_deref().map(normalize) == Some(normalize(&url)));
+        match replacement {
+            Some((_, Some(new_url))) if git::replace_remote(&remote, new_url) => {
+                println!("Updated remote `{}` to {}", remote, new_url)
+            }
+            Some(_) => eprintln!(
+                "Couldn't update remote `{}`, it still points at {}",
+                remote, url
+            ),
+            None => {}
+        }
+    }
 
     Ok(())
 }
 
+/// Exits before anything is sent if the provider would reject the name, and shows the slug if it differs from the name.
+fn check_name(config: &dyn Provider) {
+    if config.name().is_empty() {
+        eprintln!("No --name was given and it couldn't be inferred since the current directory isn't inside a git repository.");
+        exit(22);
+    }
+
+    match config.slug() {
+        Ok(slug) => {
+            if slug != config.name() {
+                println!("`{}` will be created as `{}`.", config.name(), slug);
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(22);
+        }
+    }
+}
+
+/// Exits before anything is created if the provider would reject an option, checking what needs its api
+/// only when the api is known.
+fn preflight_options(config: &dyn Provider, matches: &clap::ArgMatches) {
+    if let Err(e) = config.check_options() {
+        eprintln!("{}", e);
+        exit(22);
+    }
+
+    // A custom endpoint means the api the options would be checked against is unknown.
+    if matches.is_present("endpoint") {
+        return;
+    }
+
+    if let Err(e) = config.preflight(&Api::new(config)) {
+        eprintln!("{}", e);
+        exit(22);
+    }
+}
+
+/// Exits before the repo is created if a pre-hook fails.
+fn run_pre_hooks(hooks: &Hooks, provider: &str, config: &dyn Provider, remote: Option<&str>) {
+    if let Err(e) = hooks.run_pre(&hook::env(provider, config, &Value::Null, remote)) {
+        eprintln!("{}", e);
+        exit(22);
+    }
+}
+
+/// Looks for an existing repo before creating one, exiting unless `--use_existing` lets it be set as the remote.
+fn find_existing(config: &dyn Provider, matches: &clap::ArgMatches) -> Option<Value> {
+    // A custom endpoint means the api the repo would be looked up in is unknown.
+    if matches.is_present("endpoint") {
+        return None;
+    }
+
+    match action::existing(config, &Api::new(config)) {
+        Ok(Some(repo)) => {
+            let url = config.repo_urls(&repo).web.unwrap_or_default();
+            if matches.is_present("use_existing") {
+                println!("Repo already exists, using it: {}", url);
+                Some(repo)
+            } else {
+                eprintln!("Already exists: {}", url);
+                exit(17);
+            }
+        }
+        Ok(None) => None,
+        Err(e) => {
+            eprintln!("Couldn't check whether the repo already exists: {}", e);
+            None
+        }
+    }
+}
+
+fn existing_remote_url(config: &dyn Provider, repo: &Value, matches: &clap::ArgMatches) -> String {
+    remote_url(config, repo, matches).unwrap_or_else(|| {
+        eprintln!("The provider didn't respond with a url to set as the remote.");
+        exit(42);
+    })
+}
+
+/// The url of a repo to set as the remote, in the format `--ssh_remote_format` asks for.
+fn remote_url(config: &dyn Provider, repo: &Value, matches: &clap::ArgMatches) -> Option<String> {
+    let urls = config.repo_urls(repo);
+    if matches.is_present("ssh_remote_format") {
+        urls.ssh
+    } else {
+        urls.https.or(urls.web)
+    }
+}
+
+/// Exits before anything is created if the remote can't be set afterwards.
+fn preflight_remote(mode: RemoteMode, name: &str) {
+    if let Err(e) = check_remote(mode, name) {
+        eprintln!("{}", e);
+        exit(17);
+    }
+}
+
+fn write_remote(mode: RemoteMode, name: &str, url: &str) {
+    if !set_remote(mode, name, url) {
+        eprintln!("Failed to set remote.");
+        exit(404);
+    }
+}
+
 fn configure_request(config: &dyn Provider, matches: &clap::ArgMatches) -> reqwest::RequestBuilder {
     let client = reqwest::Client::new();
     let endpoint = if let Some(e) = matches.value_of("endpoint") {
@@ -41,179 +434,92 @@ fn configure_request(config: &dyn Provider, matches: &clap::ArgMatches) -> reqwe
     request
 }
 
-fn handle_result(result: reqwest::Response, config: &dyn Provider, matches: &clap::ArgMatches) {
+/// Finishes setting up a created repo with the provider's follow-up steps, warning about each one that fails.
+///
+/// The repo exists by now, so failures don't stop the remote being set. Returns whether every step succeeded.
+fn post_create(config: &dyn Provider, repo: &Value) -> bool {
+    let summary = config.repo_summary(repo);
+    if summary.name.is_empty() {
+        let skipped = config.post_create(&summary).len();
+        if skipped > 0 {
+            eprintln!("The provider didn't respond with the created repo's name, so {} follow-up steps were skipped.", skipped);
+        }
+        return skipped == 0;
+    }
+
+    let api = Api::new(config);
+    let mut ok = true;
+    for step in config.post_create(&summary) {
+        if let Err(e) = step.run(&api) {
+            eprintln!("Couldn't {}: {}", step.description, e);
+            ok = false;
+        }
+    }
+    ok
+}
+
+/// Reports the outcome of the creation request and returns the url to use as a remote, along with the created repo.
+fn handle_result(
+    result: reqwest::Response,
+    config: &dyn Provider,
+    matches: &clap::ArgMatches,
+) -> (String, Value) {
+    creation_result(result, config, matches).unwrap_or_else(|(message, code)| {
+        eprintln!("{}", message);
+        exit(code);
+    })
+}
+
+/// The url to use as a remote and the created repo, or why creating it failed along with the code
+/// to exit with.
+fn creation_result(
+    mut result: reqwest::Response,
+    config: &dyn Provider,
+    matches: &clap::ArgMatches,
+) -> Result<(String, Value), (String, i32)> {
     let status = result.status();
-    let headers = result.headers();
+    let headers = result.headers().clone();
+    let headers = &headers;
     match status {
         StatusCode::OK | StatusCode::CREATED => {
-            let apiloc = config.extract_url(&headers);
+            // Prefer the urls in the created repo, the headers are a fallback for providers that don't send them.
+            let repo = result.json::<Value>().unwrap_or(Value::Null);
+            let urls = config.repo_urls(&repo);
+            let apiloc = urls
+                .web
+                .clone()
+                .unwrap_or_else(|| config.extract_url(headers));
             let (remote_url, can_use_ssh) = match (
-                config.ssh_url(&headers),
+                urls.ssh.or_else(|| config.ssh_url(headers)),
                 matches.is_present("ssh_remote_format"),
             ) {
                 (Some(url), true) => (url, true),
-                _ => (config.extract_url(&headers), false),
+                _ => (
+                    urls.https
+                        .or(urls.web)
+                        .unwrap_or_else(|| config.extract_url(headers)),
+                    false,
+                ),
             };
             println!("Repo created: {}", apiloc);
-            let remote_name = matches
-                .value_of("remote_name")
-                .expect("This should default to origin, so something is wrong.");
-
-            if matches.is_present("set_remote") {
-                if matches.is_present("ssh_remote_format") && !can_use_ssh {
-                    eprintln!("Can't use ssh format with this provider.");
-                    exit(22);
-                }
-
-                if !add_remote(remote_name, &remote_url) {
-                    eprintln!("Failed to add remote.");
-                    exit(404);
-                }
+
+            if matches.is_present("set_remote")
+                && matches.is_present("ssh_remote_format")
+                && !can_use_ssh
+            {
+                return Err(("Can't use ssh format with this provider.".to_string(), 22));
             }
+
+            Ok((remote_url, repo))
         }
         StatusCode::UNPROCESSABLE_ENTITY | StatusCode::BAD_REQUEST => {
-            eprintln!("The provider had an issue processing this request. Perhaps the repository already exists, or you're using an unsupported option. e.g. Enabling projects on a repo in an org that has them disabled.");
-            exit(2);
+            Err(("The provider had an issue processing this request. Perhaps the repository already exists, or you're using an unsupported option. e.g. Enabling projects on a repo in an org that has them disabled.".to_string(), 2))
         }
         StatusCode::UNAUTHORIZED => {
-            eprintln!("You are unauthorized to create that repo.");
-            exit(3);
+            Err(("You are unauthorized to create that repo.".to_string(), 3))
         }
         _ => {
-            eprintln!("An unknown response was sent by the provider.");
-            exit(42);
+            Err(("An unknown response was sent by the provider.".to_string(), 42))
         }
-    };
-}
-
-#[cfg(test)]
-mod test {
-    use super::*;
-    fn check_success(matches: clap::ArgMatches) {
-        let config = Gitpo::from_matches(&matches);
-        let config = config.as_provider();
-
-        let request = configure_request(config, &matches);
-        let result = request.send().unwrap();
-
-        let status = result.status();
-
-        if status != StatusCode::CREATED && status != StatusCode::OK {
-            eprintln!("Status: {}", status);
-            panic!();
-        }
-    }
-
-    #[test]
-    fn github_integration() {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        let secs = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let matches = cli::get_app().get_matches_from(vec![
-            "gitpub",
-            "github",
-            "-t",
-            include_str!("../test_config.txt")
-                .trim_end()
-                .split("\n")
-                .nth(0)
-                .unwrap()
-                .split(" ")
-                .nth(1)
-                .unwrap(),
-            "--disable_merge",
-            "--disable_rebase",
-            "-i",
-            "-w",
-            "-r",
-            "-d",
-            &format!("Description {}", secs),
-            "--gitignore",
-            "Rust",
-            "--homepage",
-            &format!("Homepage {}", secs),
-            "--license",
-            "MIT",
-            "-n",
-            &format!("Test Repo {}", secs),
-        ]);
-
-        check_success(matches);
-    }
-
-    #[test]
-    fn github_integration_disable_squash() {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        let secs = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let matches = cli::get_app().get_matches_from(vec![
-            "gitpub",
-            "github",
-            "-t",
-            include_str!("../test_config.txt")
-                .trim_end()
-                .split("\n")
-                .nth(0)
-                .unwrap()
-                .split(" ")
-                .nth(1)
-                .unwrap(),
-            "--disable_squash",
-            "-n",
-            &format!("Test Disable Squash {}", secs),
-        ]);
-
-        check_success(matches);
-    }
-
-    #[test]
-    fn gitlab() {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        let secs = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let matches = cli::get_app().get_matches_from(vec![
-            "gitpub",
-            "gitlab",
-            "-t",
-            include_str!("../test_config.txt")
-                .trim_end()
-                .split("\n")
-                .nth(1)
-                .unwrap()
-                .split(" ")
-                .nth(1)
-                .unwrap(),
-            "--disable_container_registry",
-            "--disable_jobs",
-            "--disable_merge",
-            "--disable_shared_runners",
-            "--disable_snippets",
-            "--discussion_resolution_required",
-            "-i",
-            "-w",
-            "-r",
-            "--large_file_support",
-            "--merge_request_link_on_commit",
-            "--mirror",
-            "--mirror_triggers_builds",
-            "--pipeline_success_required",
-            "--public_builds",
-            "--request_access_enabled",
-            "-d", &format!("Description {}", secs),
-            "--ci_config_path", "./example",
-            "--default_branch", "dev",
-            // "--merge_method", "ff",
-            "-n",
-            &format!("Test Repo {}", secs),
-            "--visibility", "private",
-        ]);
-
-        check_success(matches);
     }
 }
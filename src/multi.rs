@@ -0,0 +1,92 @@
+use crate::cli;
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+pub const PROVIDERS: &[&str] = &["github", "gitlab", "bitbucket"];
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("multi")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Create the same repo on several providers. The first provider is the primary, the rest are mirrors.")
+        .arg(
+            Arg::with_name("name")
+                .short("n")
+                .long("name")
+                .help("The name of the new repository. Defaults to the name of the git toplevel directory.")
+                .takes_value(true)
+        ).arg(
+            Arg::with_name("description")
+                .short("d")
+                .long("description")
+                .help("A short description of the repository.")
+                .takes_value(true)
+        ).arg(
+            Arg::with_name("private")
+                .short("p")
+                .long("private")
+                .help("Sets the repository to private on every provider.")
+        ).arg(
+            Arg::with_name("providers")
+                .long("providers")
+                .help("The providers to create the repo on, primary first. Tokens are read from each provider's env variables. `--providers github gitlab bitbucket`")
+                .takes_value(true)
+                .multiple(true)
+                .required(true)
+                .possible_values(PROVIDERS)
+        ).arg(
+            Arg::with_name("remote_per_provider")
+                .long("remote_per_provider")
+                .help("With --set_remote, adds one remote named after each provider instead of push urls on a single remote.")
+        )
+}
+
+/// Parses a provider subcommand for each requested provider, sharing the name, description and privacy.
+///
+/// Returns the provider name alongside the top level matches for that provider.
+pub fn provider_matches(
+    matches: &ArgMatches,
+) -> Result<Vec<(&'static str, ArgMatches<'static>)>, clap::Error> {
+    let mut providers: Vec<&'static str> = Vec::new();
+    for provider in matches.values_of("providers").into_iter().flatten() {
+        let provider = PROVIDERS
+            .iter()
+            .find(|p| **p == provider)
+            .expect("Providers are limited to possible values. This should be unreachable.");
+        if !providers.contains(provider) {
+            providers.push(provider);
+        }
+    }
+
+    providers
+        .into_iter()
+        .map(|provider| {
+            cli::get_app()
+                .get_matches_from_safe(provider_args(provider, matches))
+                .map(|m| (provider, m))
+        })
+        .collect()
+}
+
+fn provider_args(provider: &str, matches: &ArgMatches) -> Vec<String> {
+    let mut args = vec!["gitpub".to_string(), provider.to_string()];
+    if let Some(name) = matches.value_of("name") {
+        args.push("--name".to_string());
+        args.push(name.to_string());
+    }
+
+    if let Some(description) = matches.value_of("description") {
+        args.push("--description".to_string());
+        args.push(description.to_string());
+    }
+
+    if matches.is_present("private") {
+        match provider {
+            "gitlab" => {
+                args.push("--visibility".to_string());
+                args.push("private".to_string());
+            }
+            _ => args.push("--private".to_string()),
+        }
+    }
+
+    args
+}
@@ -0,0 +1,234 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use clap::ArgMatches;
+use serde_json::Value as Json;
+use toml::Value as Toml;
+
+/// Metadata about the local project, used to fill in options that weren't passed.
+#[derive(Default, Debug, PartialEq)]
+pub struct Project {
+    /// The name of the git toplevel directory.
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub homepage: Option<String>,
+    /// An SPDX license identifier, only set when the manifest names a single license.
+    pub license: Option<String>,
+    pub keywords: Vec<String>,
+}
+
+impl Project {
+    /// Reads the project from the git toplevel of the current directory.
+    ///
+    /// Manifest metadata is read from the first of `Cargo.toml`, `package.json` or `pyproject.toml` found in the root.
+    pub fn detect(read_manifest: bool) -> Project {
+        let root = match toplevel() {
+            Some(root) => root,
+            None => return Project::default(),
+        };
+
+        let mut project = if read_manifest {
+            Project::from_dir(&root)
+        } else {
+            Project::default()
+        };
+        project.name = root
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(str::to_string);
+        project
+    }
+
+    /// Reads manifest metadata from a directory.
+    pub fn from_dir(dir: &Path) -> Project {
+        if let Some(cargo) = read_toml(&dir.join("Cargo.toml")) {
+            Project::from_cargo(&cargo)
+        } else if let Some(package) = fs::read_to_string(dir.join("package.json"))
+            .ok()
+            .and_then(|s| serde_json::from_str::<Json>(&s).ok())
+        {
+            Project::from_package_json(&package)
+        } else if let Some(pyproject) = read_toml(&dir.join("pyproject.toml")) {
+            Project::from_pyproject(&pyproject)
+        } else {
+            Project::default()
+        }
+    }
+
+    fn from_cargo(cargo: &Toml) -> Project {
+        let package = match cargo.get("package") {
+            Some(package) => package,
+            None => return Project::default(),
+        };
+        Project {
+            name: None,
+            description: toml_str(package.get("description")),
+            homepage: toml_str(package.get("homepage")),
+            license: toml_str(package.get("license")).and_then(single_license),
+            keywords: toml_strs(package.get("keywords")),
+        }
+    }
+
+    fn from_package_json(package: &Json) -> Project {
+        Project {
+            name: None,
+            description: package["description"].as_str().map(str::to_string),
+            homepage: package["homepage"].as_str().map(str::to_string),
+            license: package["license"]
+                .as_str()
+                .map(str::to_string)
+                .and_then(single_license),
+            keywords: package["keywords"]
+                .as_array()
+                .map(|k| {
+                    k.iter()
+                        .filter_map(|x| x.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    fn from_pyproject(pyproject: &Toml) -> Project {
+        // PEP 621 metadata lives in [project], older poetry projects use [tool.poetry].
+        let table = pyproject
+            .get("project")
+            .or_else(|| pyproject.get("tool").and_then(|t| t.get("poetry")));
+        let table = match table {
+            Some(table) => table,
+            None => return Project::default(),
+        };
+
+        let license = table.get("license").and_then(|l| match l {
+            Toml::String(s) => Some(s.to_string()),
+            Toml::Table(t) => toml_str(t.get("text")),
+            _ => None,
+        });
+        let homepage = toml_str(table.get("homepage")).or_else(|| {
+            table
+                .get("urls")
+                .and_then(|urls| toml_str(urls.get("Homepage").or_else(|| urls.get("homepage"))))
+        });
+
+        Project {
+            name: None,
+            description: toml_str(table.get("description")),
+            homepage,
+            license: license.and_then(single_license),
+            keywords: toml_strs(table.get("keywords")),
+        }
+    }
+}
+
+/// Returns the repository name, falling back to the detected project name.
+///
+/// The name is empty if neither is available, which is reported when the name is checked.
+pub fn name<'a>(matches: &'a ArgMatches, project: &'a Project) -> &'a str {
+    matches
+        .value_of("name")
+        .or(project.name.as_deref())
+        .unwrap_or_default()
+}
+
+/// The root of the git repository containing the current directory.
+pub fn toplevel() -> Option<PathBuf> {
+    Command::new("git")
+        .arg("rev-parse")
+        .arg("--show-toplevel")
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|dir| PathBuf::from(dir.trim()))
+}
+
+fn read_toml(path: &Path) -> Option<Toml> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|s| s.parse::<Toml>().ok())
+}
+
+fn toml_str(value: Option<&Toml>) -> Option<String> {
+    value.and_then(Toml::as_str).map(str::to_string)
+}
+
+fn toml_strs(value: Option<&Toml>) -> Vec<String> {
+    value
+        .and_then(Toml::as_array)
+        .map(|k| {
+            k.iter()
+                .filter_map(|x| x.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Expressions like `MIT OR Apache-2.0` can't be mapped to a single license template.
+fn single_license(license: String) -> Option<String> {
+    if license.contains(char::is_whitespace) || license.contains('/') {
+        None
+    } else {
+        Some(license)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cargo_manifest() {
+        let cargo = r#"
+            [package]
+            name = "gitpub"
+            description = "A CLI app to create remote git repositories."
+            license = "GPL-3.0"
+            keywords = ["git", "cli"]
+        "#
+        .parse::<Toml>()
+        .unwrap();
+
+        let project = Project::from_cargo(&cargo);
+        assert_eq!(
+            project.description.as_deref(),
+            Some("A CLI app to create remote git repositories.")
+        );
+        assert_eq!(project.homepage, None);
+        assert_eq!(project.license.as_deref(), Some("GPL-3.0"));
+        assert_eq!(project.keywords, vec!["git", "cli"]);
+    }
+
+    #[test]
+    fn package_json_with_license_expression() {
+        let package = serde_json::from_str::<Json>(
+            r#"{ "description": "desc", "homepage": "https://example.com", "license": "MIT OR Apache-2.0" }"#,
+        )
+        .unwrap();
+
+        let project = Project::from_package_json(&package);
+        assert_eq!(project.homepage.as_deref(), Some("https://example.com"));
+        assert_eq!(project.license, None);
+        assert!(project.keywords.is_empty());
+    }
+
+    #[test]
+    fn pyproject_urls() {
+        let pyproject = r#"
+            [project]
+            description = "desc"
+            license = { text = "MIT" }
+            keywords = ["python"]
+
+            [project.urls]
+            Homepage = "https://example.com"
+        "#
+        .parse::<Toml>()
+        .unwrap();
+
+        let project = Project::from_pyproject(&pyproject);
+        assert_eq!(project.homepage.as_deref(), Some("https://example.com"));
+        assert_eq!(project.license.as_deref(), Some("MIT"));
+        assert_eq!(project.keywords, vec!["python"]);
+    }
+}
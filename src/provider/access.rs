@@ -0,0 +1,88 @@
+//! Parses `--collaborator` and `--team` into a username or team plus a permission, which each
+//! provider maps onto its own roles or access levels.
+use clap::{Arg, ArgMatches};
+
+/// A permission in gitpub's vocabulary, which each provider maps onto its own roles.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Permission {
+    Read,
+    Triage,
+    Write,
+    Maintain,
+    Admin,
+}
+
+impl Permission {
+    /// Parses github, gitlab or bitbucket names for a permission, like `push`, `developer` or `write`.
+    pub fn parse(name: &str) -> Option<Permission> {
+        match name {
+            "read" | "pull" | "reporter" => Some(Permission::Read),
+            "triage" => Some(Permission::Triage),
+            "write" | "push" | "developer" => Some(Permission::Write),
+            "maintain" | "maintainer" => Some(Permission::Maintain),
+            "admin" | "owner" => Some(Permission::Admin),
+            _ => None,
+        }
+    }
+}
+
+/// A user or team and the permission they're given, parsed from `name[:permission]`.
+pub struct Grant<'a> {
+    pub name: &'a str,
+    pub permission: Permission,
+}
+
+impl<'a> Grant<'a> {
+    fn parse(value: &'a str) -> Grant<'a> {
+        let (name, permission) = match value.split_once(':') {
+            Some((name, permission)) => (name, Permission::parse(permission)),
+            None => (value, Some(Permission::Write)),
+        };
+        Grant {
+            name,
+            permission: permission
+                .expect("Permissions are validated by clap. This should be unreachable."),
+        }
+    }
+
+    /// The grants passed to an arg like `collaborator`.
+    pub fn from_matches(matches: &'a ArgMatches<'a>, name: &str) -> Vec<Grant<'a>> {
+        matches
+            .values_of(name)
+            .map(|values| values.map(Grant::parse).collect())
+            .unwrap_or_default()
+    }
+}
+
+fn validate(value: String) -> Result<(), String> {
+    match value.split_once(':') {
+        Some(("", _)) => Err("The name before the `:` is missing.".to_string()),
+        Some((_, permission)) if Permission::parse(permission).is_none() => Err(format!(
+            "`{}` isn't a permission. Use read, triage, write, maintain or admin.",
+            permission
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub fn collaborator_arg() -> Arg<'static, 'static> {
+    Arg::with_name("collaborator")
+        .long("collaborator")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .value_name("user:permission")
+        .validator(validate)
+        .help("Gives a user access after creation, ex `--collaborator octocat:admin`. Permissions are read, triage, write, maintain or admin, or the provider's own names. Defaults to write.")
+}
+
+pub fn team_arg() -> Arg<'static, 'static> {
+    Arg::with_name("team")
+        .long("team")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .value_name("team:permission")
+        .validator(validate)
+        .help("Gives a team or group access after creation, ex `--team platform:maintain`. Takes the same permissions as --collaborator.")
+}
@@ -1,7 +1,19 @@
-use super::Provider;
-use clap::{App, Arg, ArgMatches, SubCommand};
+use super::access::{self, Grant, Permission};
+use super::boilerplate::{self, Boilerplate};
+use super::deploy_key::{self, DeployKey};
+use super::protect::{self, Protection};
+use super::seed::{self, Seed};
+use super::webhook::{self, Webhook};
+use super::{slug, LikeMatches, Provider, RepoSummary, RepoUrls, Request, Settings, Step};
+use crate::action;
+use crate::api::{Api, Response};
+use crate::project::{self, Project};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use reqwest::multipart::{Form, Part};
+use reqwest::Method;
 use serde::Serialize;
-use serde_json::to_string;
+use serde_json::{json, to_string, Value};
+use std::error::Error;
 
 #[derive(Serialize)]
 pub struct BitbucketArgs<'a> {
@@ -9,7 +21,11 @@ pub struct BitbucketArgs<'a> {
     #[serde(skip_serializing)]
     token: &'a str,
     #[serde(skip_serializing)]
+    api_url: &'a str,
+    #[serde(skip_serializing)]
     username: &'a str,
+    #[serde(skip_serializing)]
+    workspace: Option<&'a str>,
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<&'a str>,
     is_private: bool,
@@ -20,9 +36,83 @@ pub struct BitbucketArgs<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     scm: Option<&'a str>,
     language: Option<&'a str>,
+    #[serde(skip_serializing)]
+    protection: Protection<'a>,
+    #[serde(skip_serializing)]
+    users: Vec<Grant<'a>>,
+    #[serde(skip_serializing)]
+    groups: Vec<Grant<'a>>,
+    #[serde(skip_serializing)]
+    webhooks: Vec<Webhook<'a>>,
+    #[serde(skip_serializing)]
+    deploy_key: Option<DeployKey<'a>>,
+    #[serde(skip_serializing)]
+    seed: Seed<'a>,
+    #[serde(skip_serializing)]
+    boilerplate: Boilerplate,
+}
+
+/// Bitbucket's name for a permission.
+fn permission(permission: Permission) -> &'static str {
+    match permission {
+        Permission::Read | Permission::Triage => "read",
+        Permission::Write | Permission::Maintain => "write",
+        Permission::Admin => "admin",
+    }
+}
+
+/// Bitbucket's events for one of github's, passing bitbucket's own like `repo:fork` through.
+fn hook_events(event: &str) -> Vec<&str> {
+    match event {
+        "push" => vec!["repo:push"],
+        "fork" => vec!["repo:fork"],
+        "pull_request" => vec![
+            "pullrequest:created",
+            "pullrequest:updated",
+            "pullrequest:fulfilled",
+            "pullrequest:rejected",
+        ],
+        "pull_request_review_comment" => vec!["pullrequest:comment_created"],
+        "issues" => vec!["issue:created", "issue:updated"],
+        "issue_comment" => vec!["issue:comment_created"],
+        other => vec![other],
+    }
+}
+
+/// The account id of a workspace member given by nickname, account id or uuid.
+fn account_id(api: &Api, workspace: &str, user: &str) -> Result<String, Box<dyn Error>> {
+    let members = api
+        .get(&format!("/workspaces/{}/members?pagelen=100", workspace))?
+        .into_json()?;
+    members["values"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|member| &member["user"])
+        .find(|u| u["nickname"] == user || u["account_id"] == user || u["uuid"] == user)
+        .and_then(|u| u["account_id"].as_str())
+        .map(str::to_string)
+        .ok_or_else(|| {
+            format!(
+                "`{}` isn't a member of the `{}` workspace.",
+                user, workspace
+            )
+            .into()
+        })
+}
+
+impl<'a> BitbucketArgs<'a> {
+    fn repo_slug(&self) -> String {
+        slug::bitbucket(self.name).unwrap_or_else(|_| self.name.to_string())
+    }
+
+    fn workspace(&self) -> &str {
+        self.workspace.unwrap_or(self.username)
+    }
 }
 
-const ENDPOINT: &str = "https://api.bitbucket.org/2.0/repositories/{username}/{slug}";
+const API_URL: &str = "https://api.bitbucket.org/2.0";
+const ENDPOINT: &str = "/repositories/{workspace}/{slug}";
 
 impl<'a> Provider for BitbucketArgs<'a> {
     fn payload(&self) -> String {
@@ -30,13 +120,18 @@ impl<'a> Provider for BitbucketArgs<'a> {
     }
 
     fn endpoint(&self) -> String {
-        ENDPOINT
-            .replace("{username}", &self.username)
-            .replace("{slug}", &self.name)
+        let path = ENDPOINT
+            .replace("{workspace}", self.workspace())
+            .replace("{slug}", &self.repo_slug());
+        format!("{}{}", self.api_url, path)
     }
 
     fn extract_url(&self, _: &reqwest::header::HeaderMap) -> String {
-        format!("https://bitbucket.org/{}/{}", &self.username, &self.name)
+        format!(
+            "https://bitbucket.org/{}/{}",
+            self.workspace(),
+            self.repo_slug()
+        )
     }
 
     fn token(&self) -> String {
@@ -50,43 +145,359 @@ impl<'a> Provider for BitbucketArgs<'a> {
         "Authorization".to_string()
     }
 
+    fn name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn slug(&self) -> Result<String, String> {
+        slug::bitbucket(self.name)
+    }
+
+    fn api_url(&self) -> String {
+        self.api_url.to_string()
+    }
+
+    fn repo_path(&self, _: &Api) -> Result<String, Box<dyn Error>> {
+        Ok(format!(
+            "/repositories/{}/{}",
+            self.workspace(),
+            self.slug()?
+        ))
+    }
+
+    fn repo_urls(&self, repo: &Value) -> RepoUrls {
+        let clone_url = |name: &str| {
+            repo["links"]["clone"]
+                .as_array()
+                .and_then(|links| links.iter().find(|link| link["name"] == name))
+                .and_then(|link| link["href"].as_str())
+                .map(str::to_string)
+        };
+        RepoUrls {
+            web: repo["links"]["html"]["href"].as_str().map(str::to_string),
+            https: clone_url("https"),
+            ssh: clone_url("ssh"),
+        }
+    }
+
+    fn list_path(&self) -> String {
+        format!("/repositories/{}?pagelen=100", self.workspace())
+    }
+
+    fn list_page(&self, page: &Value) -> Vec<Value> {
+        page["values"].as_array().cloned().unwrap_or_default()
+    }
+
+    fn next_page(&self, response: &Response, _: &str) -> Option<String> {
+        response.body["next"].as_str().map(str::to_string)
+    }
+
+    fn repo_summary(&self, repo: &Value) -> RepoSummary {
+        RepoSummary {
+            name: repo["full_name"].as_str().unwrap_or_default().to_string(),
+            private: repo["is_private"].as_bool().unwrap_or_default(),
+            archived: false,
+            description: repo["description"].as_str().unwrap_or_default().to_string(),
+            url: self.repo_urls(repo).web.unwrap_or_default(),
+            default_branch: repo["mainbranch"]["name"].as_str().map(str::to_string),
+        }
+    }
+
+    fn full_repo_path(&self, full_name: &str) -> String {
+        format!("/repositories/{}", full_name)
+    }
+
+    fn settings(&self, repo: &Value) -> Settings {
+        let mut settings = Settings::default();
+        settings.value("name", repo["name"].as_str());
+        settings.value("workspace", repo["workspace"]["slug"].as_str());
+        settings.value("description", repo["description"].as_str());
+        settings.flag("private", repo["is_private"] == true);
+        settings.flag("disable_wiki", repo["has_wiki"] == false);
+        settings.flag("disable_issues", repo["has_issues"] == false);
+        settings.value("fork_policy", repo["fork_policy"].as_str());
+        settings.value("scm", repo["scm"].as_str());
+        settings.value("language", repo["language"].as_str());
+        settings
+    }
+
+    fn post_create(&self, created: &RepoSummary) -> Vec<Step<'_>> {
+        let full_name = created.name.as_str();
+        let protection = &self.protection;
+        let mut steps = Vec::new();
+        // The src endpoint takes each file as a form field named after its path.
+        if !self.seed.is_empty() {
+            let repo = self.full_repo_path(full_name);
+            steps.push(Step::with_api("add the seed files", move |api| {
+                let mut form = Form::new().text("message", seed::MESSAGE);
+                for file in self.seed.read()? {
+                    let name = file.path.rsplit('/').next().unwrap_or_default().to_string();
+                    form = form.part(file.path, Part::bytes(file.contents).file_name(name));
+                }
+                api.post_form(&format!("{}/src", repo), form)?.into_json()?;
+                Ok(())
+            }));
+        }
+        // Restrictions match branches by pattern, so they don't have to exist yet.
+        for branch in &protection.branches {
+            let mut restrictions = vec![
+                json!({ "kind": "force", "pattern": branch }),
+                json!({ "kind": "delete", "pattern": branch }),
+            ];
+            if protection.required_reviews > 0 {
+                // Nobody is allowed to push, so changes have to go through a pull request.
+                restrictions
+                    .push(json!({ "kind": "push", "pattern": branch, "users": [], "groups": [] }));
+                restrictions.push(json!({
+                    "kind": "require_approvals_to_merge",
+                    "pattern": branch,
+                    "value": protection.required_reviews,
+                }));
+            }
+            if !protection.status_checks.is_empty() {
+                restrictions.push(json!({
+                    "kind": "require_passing_builds_to_merge",
+                    "pattern": branch,
+                    "value": 1,
+                }));
+            }
+            if protection.dismiss_stale_reviews {
+                restrictions.push(
+                    json!({ "kind": "reset_pullrequest_approvals_on_change", "pattern": branch }),
+                );
+            }
+
+            for restriction in restrictions {
+                steps.push(Step::new(
+                    format!("protect `{}` with {}", branch, restriction["kind"]),
+                    (
+                        Method::POST,
+                        format!("{}/branch-restrictions", self.full_repo_path(full_name)),
+                        Some(restriction),
+                    ),
+                ));
+            }
+        }
+        for user in &self.users {
+            let repo = self.full_repo_path(full_name);
+            steps.push(Step::lookup(
+                format!("give `{}` access", user.name),
+                move |api| {
+                    let account_id = account_id(api, self.workspace(), user.name)?;
+                    Ok((
+                        Method::PUT,
+                        format!("{}/permissions-config/users/{}", repo, account_id),
+                        Some(json!({ "permission": permission(user.permission) })),
+                    ))
+                },
+            ));
+        }
+        for group in &self.groups {
+            steps.push(Step::new(
+                format!("give the `{}` group access", group.name),
+                (
+                    Method::PUT,
+                    format!(
+                        "{}/permissions-config/groups/{}",
+                        self.full_repo_path(full_name),
+                        group.name
+                    ),
+                    Some(json!({ "permission": permission(group.permission) })),
+                ),
+            ));
+        }
+        for hook in &self.webhooks {
+            let mut payload = json!({
+                "description": "gitpub",
+                "url": hook.url,
+                "active": true,
+                "events": hook.events.iter().flat_map(|event| hook_events(event)).collect::<Vec<_>>(),
+            });
+            if let Some(secret) = &hook.secret {
+                payload["secret"] = json!(secret);
+            }
+            steps.push(Step::new(
+                format!("add the `{}` webhook", hook.url),
+                (
+                    Method::POST,
+                    format!("{}/hooks", self.full_repo_path(full_name)),
+                    Some(payload),
+                ),
+            ));
+        }
+        if let Some(key) = &self.deploy_key {
+            let repo = self.full_repo_path(full_name);
+            let comment = full_name.to_string();
+            steps.push(Step::lookup(
+                format!("add the `{}` deploy key", key.title()),
+                move |_| {
+                    let public_key = key.public_key(&comment)?;
+                    Ok((
+                        Method::POST,
+                        format!("{}/deploy-keys", repo),
+                        Some(json!({ "label": key.title(), "key": public_key })),
+                    ))
+                },
+            ));
+        }
+        steps.extend(self.boilerplate.local_step());
+        steps
+    }
+
+    fn archive_request(&self, full_name: &str, archived: bool) -> Result<Request, Box<dyn Error>> {
+        // Bitbucket has no archived state, so the closest thing is hiding the repo.
+        if !archived {
+            return Err("Bitbucket can't archive repos, so there's nothing to unarchive.".into());
+        }
+        eprintln!(
+            "Bitbucket can't archive repos, making `{}` private instead.",
+            full_name
+        );
+        Ok((
+            Method::PUT,
+            self.full_repo_path(full_name),
+            Some(json!({ "is_private": true })),
+        ))
+    }
+
+    fn rename_request(&self, full_name: &str, new_name: &str) -> Request {
+        (
+            Method::PUT,
+            self.full_repo_path(full_name),
+            Some(json!({ "name": new_name })),
+        )
+    }
+
+    fn transfer_request(&self, _: &str, _: &str) -> Result<Request, Box<dyn Error>> {
+        Err("Bitbucket's api can't transfer repos, use the repository settings on bitbucket.org instead.".into())
+    }
+
+    fn fork_payload(&self) -> Value {
+        json!({ "workspace": { "slug": self.workspace() } })
+    }
+
     fn ssh_url(&self, _: &reqwest::header::HeaderMap) -> Option<String> {
         Some(format!(
-            "git@bitbucket.com:{}/{}.git",
-            &self.username, &self.name
+            "git@bitbucket.org:{}/{}.git",
+            self.workspace(),
+            self.repo_slug()
         ))
     }
 }
 
+fn name_arg() -> Arg<'static, 'static> {
+    Arg::with_name("name")
+        .short("n")
+        .long("name")
+        .help("The name of the new repository. Defaults to the name of the git toplevel directory.")
+        .takes_value(true)
+}
+
+fn username_arg() -> Arg<'static, 'static> {
+    Arg::with_name("username")
+        .long("username")
+        .help("Your bitbucket username. Alternatively read from BITBUCKET_USERNAME env variable.")
+        .takes_value(true)
+        .required(true)
+        .env("BITBUCKET_USERNAME")
+}
+
+fn api_url_arg() -> Arg<'static, 'static> {
+    Arg::with_name("api_url")
+        .long("api_url")
+        .help("The root of the bitbucket api, useful for self-hosted instances. Alternatively read from BITBUCKET_API_URL env variable.")
+        .env("BITBUCKET_API_URL")
+        .takes_value(true)
+        .default_value(API_URL)
+}
+
+fn workspace_arg() -> Arg<'static, 'static> {
+    Arg::with_name("workspace")
+        .long("workspace")
+        .help("The workspace the repo belongs to. Defaults to your username.")
+        .takes_value(true)
+}
+
+fn token_arg() -> Arg<'static, 'static> {
+    Arg::with_name("token")
+        .short("t")
+        .long("token")
+        .help("A personal access token. Alternatively read from BITBUCKET_REPO_TOKEN env variable.")
+        .env("BITBUCKET_REPO_TOKEN")
+        .takes_value(true)
+        .required(true)
+}
+
 pub fn subcommand() -> App<'static, 'static> {
     SubCommand::with_name("bitbucket")
         .version(env!("CARGO_PKG_VERSION"))
         .about("Create a repo on bitbucket.")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Checks whether a repo with this name already exists.")
+                .arg(name_arg())
+                .arg(username_arg())
+                .arg(token_arg())
+                .arg(api_url_arg())
+                .arg(workspace_arg())
+        )
+        .subcommand(
+            action::list_subcommand()
+                .arg(username_arg())
+                .arg(token_arg())
+                .arg(api_url_arg())
+                .arg(workspace_arg())
+        )
+        .subcommand(
+            action::show_subcommand()
+                .arg(username_arg())
+                .arg(token_arg())
+                .arg(api_url_arg())
+        )
+        .subcommand(
+            action::archive_subcommand("archive")
+                .arg(username_arg())
+                .arg(token_arg())
+                .arg(api_url_arg())
+        )
+        .subcommand(
+            action::archive_subcommand("unarchive")
+                .arg(username_arg())
+                .arg(token_arg())
+                .arg(api_url_arg())
+        )
+        .subcommand(
+            action::rename_subcommand()
+                .arg(username_arg())
+                .arg(token_arg())
+                .arg(api_url_arg())
+        )
+        .subcommand(
+            action::transfer_subcommand()
+                .arg(username_arg())
+                .arg(token_arg())
+                .arg(api_url_arg())
+        )
+        .subcommand(
+            action::fork_subcommand()
+                .arg(username_arg())
+                .arg(token_arg())
+                .arg(api_url_arg())
+                .arg(workspace_arg().help("The workspace to fork into. Defaults to your username."))
+        )
+        .arg(name_arg())
+        .arg(username_arg())
+        .arg(token_arg())
+        .arg(api_url_arg())
+        .arg(workspace_arg())
+        .arg(action::like_arg())
+        .args(&action::unlike_args(&[
+            "private",
+            "disable_wiki",
+            "disable_issues",
+        ]))
         .arg(
-            Arg::with_name("name")
-                .short("n")
-                .long("name")
-                .help("The name of the new repository.")
-                .takes_value(true)
-                .required(true)
-        ).arg(
-            Arg::with_name("username")
-                .long("username")
-                .help("Your bitbucket username. Alternatively read from BITBUCKET_USERNAME env variable.")
-                .takes_value(true)
-                .required(true)
-                .env("BITBUCKET_USERNAME")
-        ).arg(
-            Arg::with_name("token")
-                .short("t")
-                .long("token")
-                .help(
-                    "A personal access token. Alternatively read from BITBUCKET_REPO_TOKEN env variable.",
-                )
-                .env("BITBUCKET_REPO_TOKEN")
-                .takes_value(true)
-                .required(true)
-        ).arg(
             Arg::with_name("description")
                 .short("d")
                 .long("description")
@@ -124,26 +535,59 @@ pub fn subcommand() -> App<'static, 'static> {
                 .long("language")
                 .help("Give bitbucket a hint about the programming language.")
                 .takes_value(true)
-        )
+        ).arg(
+            // Only here to explain why it's missing, instead of clap's generic error.
+            Arg::with_name("topic")
+                .long("topic")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .hidden(true)
+                .validator(|_| Err("Bitbucket repos have no topics. Use --description or --language to help people find the repo instead.".to_string()))
+        ).args(&protect::args())
+        .args(&seed::args())
+        .args(&boilerplate::args())
+        .args(&deploy_key::args())
+        .arg(webhook::arg().help("Registers a webhook after creation, ex `--webhook https://ci.example.com/hook,events=push,pull_request,secret_env=CI_SECRET`. Events use github's names, or bitbucket's own like repo:fork, and default to push. The secret is read from the named environment variable."))
+        .arg(access::collaborator_arg().help("Gives a workspace member access after creation by nickname or account id, ex `--collaborator atlas:admin`. Permissions are read, write or admin. Defaults to write."))
+        .arg(access::team_arg().help("Gives a workspace group access after creation by its slug, ex `--team developers:write`. Takes the same permissions as --collaborator."))
 }
 
-pub fn from_matches<'a>(matches: &'a ArgMatches) -> BitbucketArgs<'a> {
+pub fn from_matches<'a>(
+    matches: &'a ArgMatches<'a>,
+    project: &'a Project,
+    like: &'a Settings,
+) -> BitbucketArgs<'a> {
+    let settings = LikeMatches::new(matches, like);
+    let boilerplate = Boilerplate::from_matches(matches);
+    let mut seed = Seed::from_matches(matches);
+    boilerplate.add_to(&mut seed);
     BitbucketArgs {
-        name: matches
-            .value_of("name")
-            .expect("This property is required. This error should be unreachable."),
+        name: project::name(matches, project),
         token: matches
             .value_of("token")
             .expect("Couldn't get the token. This should be unreachable."),
+        api_url: matches.value_of("api_url").unwrap_or(API_URL),
         username: matches
             .value_of("username")
             .expect("This property is requred. This error should be unreachable."),
-        description: matches.value_of("description"),
-        is_private: matches.is_present("private"),
-        has_issues: !matches.is_present("disable_issues"),
-        has_wiki: !matches.is_present("disable_wiki"),
-        fork_policy: matches.value_of("fork_policy"),
-        scm: matches.value_of("scm"),
-        language: matches.value_of("language"),
+        workspace: matches.value_of("workspace"),
+        description: matches
+            .value_of("description")
+            .or(project.description.as_deref())
+            .or_else(|| like.value_of("description")),
+        is_private: settings.is_present("private"),
+        has_issues: !settings.is_present("disable_issues"),
+        has_wiki: !settings.is_present("disable_wiki"),
+        fork_policy: settings.value_of("fork_policy"),
+        scm: settings.value_of("scm"),
+        language: settings.value_of("language"),
+        protection: Protection::from_matches(matches),
+        users: Grant::from_matches(matches, "collaborator"),
+        groups: Grant::from_matches(matches, "team"),
+        webhooks: Webhook::from_matches(matches),
+        deploy_key: DeployKey::from_matches(matches),
+        seed,
+        boilerplate,
     }
 }
@@ -0,0 +1,506 @@
+//! License and gitignore files bundled with gitpub, so every provider can start a repo with them
+//! and names can be checked before anything is sent.
+use super::seed::Seed;
+use super::Step;
+use crate::git;
+use crate::project;
+use clap::{Arg, ArgMatches};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// License texts from the SPDX license list, keyed like github's license templates.
+/// `[year]` and `[fullname]` are filled in when the file is written.
+const LICENSES: &[(&str, &str)] = &[
+    (
+        "agpl-3.0",
+        include_str!("../../templates/licenses/agpl-3.0.txt"),
+    ),
+    (
+        "apache-2.0",
+        include_str!("../../templates/licenses/apache-2.0.txt"),
+    ),
+    (
+        "bsd-2-clause",
+        include_str!("../../templates/licenses/bsd-2-clause.txt"),
+    ),
+    (
+        "bsd-3-clause",
+        include_str!("../../templates/licenses/bsd-3-clause.txt"),
+    ),
+    (
+        "bsl-1.0",
+        include_str!("../../templates/licenses/bsl-1.0.txt"),
+    ),
+    (
+        "cc0-1.0",
+        include_str!("../../templates/licenses/cc0-1.0.txt"),
+    ),
+    (
+        "epl-2.0",
+        include_str!("../../templates/licenses/epl-2.0.txt"),
+    ),
+    (
+        "gpl-2.0",
+        include_str!("../../templates/licenses/gpl-2.0.txt"),
+    ),
+    (
+        "gpl-3.0",
+        include_str!("../../templates/licenses/gpl-3.0.txt"),
+    ),
+    ("isc", include_str!("../../templates/licenses/isc.txt")),
+    (
+        "lgpl-2.1",
+        include_str!("../../templates/licenses/lgpl-2.1.txt"),
+    ),
+    (
+        "lgpl-3.0",
+        include_str!("../../templates/licenses/lgpl-3.0.txt"),
+    ),
+    ("mit", include_str!("../../templates/licenses/mit.txt")),
+    (
+        "mpl-2.0",
+        include_str!("../../templates/licenses/mpl-2.0.txt"),
+    ),
+    (
+        "unlicense",
+        include_str!("../../templates/licenses/unlicense.txt"),
+    ),
+];
+
+/// Templates from github/gitignore, keyed by their name there.
+const GITIGNORES: &[(&str, &str)] = &[
+    (
+        "Android",
+        include_str!("../../templates/gitignore/Android.gitignore"),
+    ),
+    ("C", include_str!("../../templates/gitignore/C.gitignore")),
+    (
+        "C++",
+        include_str!("../../templates/gitignore/C++.gitignore"),
+    ),
+    (
+        "CMake",
+        include_str!("../../templates/gitignore/CMake.gitignore"),
+    ),
+    (
+        "CUDA",
+        include_str!("../../templates/gitignore/CUDA.gitignore"),
+    ),
+    (
+        "Dart",
+        include_str!("../../templates/gitignore/Dart.gitignore"),
+    ),
+    (
+        "Elixir",
+        include_str!("../../templates/gitignore/Elixir.gitignore"),
+    ),
+    (
+        "Erlang",
+        include_str!("../../templates/gitignore/Erlang.gitignore"),
+    ),
+    (
+        "Flutter",
+        include_str!("../../templates/gitignore/Flutter.gitignore"),
+    ),
+    ("Go", include_str!("../../templates/gitignore/Go.gitignore")),
+    (
+        "Gradle",
+        include_str!("../../templates/gitignore/Gradle.gitignore"),
+    ),
+    (
+        "Haskell",
+        include_str!("../../templates/gitignore/Haskell.gitignore"),
+    ),
+    (
+        "Java",
+        include_str!("../../templates/gitignore/Java.gitignore"),
+    ),
+    (
+        "Julia",
+        include_str!("../../templates/gitignore/Julia.gitignore"),
+    ),
+    (
+        "Kotlin",
+        include_str!("../../templates/gitignore/Kotlin.gitignore"),
+    ),
+    (
+        "Laravel",
+        include_str!("../../templates/gitignore/Laravel.gitignore"),
+    ),
+    (
+        "Lua",
+        include_str!("../../templates/gitignore/Lua.gitignore"),
+    ),
+    (
+        "Maven",
+        include_str!("../../templates/gitignore/Maven.gitignore"),
+    ),
+    (
+        "Nextjs",
+        include_str!("../../templates/gitignore/Nextjs.gitignore"),
+    ),
+    (
+        "Node",
+        include_str!("../../templates/gitignore/Node.gitignore"),
+    ),
+    (
+        "Objective-C",
+        include_str!("../../templates/gitignore/Objective-C.gitignore"),
+    ),
+    (
+        "OCaml",
+        include_str!("../../templates/gitignore/OCaml.gitignore"),
+    ),
+    (
+        "Perl",
+        include_str!("../../templates/gitignore/Perl.gitignore"),
+    ),
+    (
+        "Python",
+        include_str!("../../templates/gitignore/Python.gitignore"),
+    ),
+    ("R", include_str!("../../templates/gitignore/R.gitignore")),
+    (
+        "Rails",
+        include_str!("../../templates/gitignore/Rails.gitignore"),
+    ),
+    (
+        "Ruby",
+        include_str!("../../templates/gitignore/Ruby.gitignore"),
+    ),
+    (
+        "Rust",
+        include_str!("../../templates/gitignore/Rust.gitignore"),
+    ),
+    (
+        "Scala",
+        include_str!("../../templates/gitignore/Scala.gitignore"),
+    ),
+    (
+        "Swift",
+        include_str!("../../templates/gitignore/Swift.gitignore"),
+    ),
+    (
+        "Terraform",
+        include_str!("../../templates/gitignore/Terraform.gitignore"),
+    ),
+    (
+        "TeX",
+        include_str!("../../templates/gitignore/TeX.gitignore"),
+    ),
+    (
+        "Unity",
+        include_str!("../../templates/gitignore/Unity.gitignore"),
+    ),
+    (
+        "UnrealEngine",
+        include_str!("../../templates/gitignore/UnrealEngine.gitignore"),
+    ),
+    (
+        "VisualStudio",
+        include_str!("../../templates/gitignore/VisualStudio.gitignore"),
+    ),
+    (
+        "Zig",
+        include_str!("../../templates/gitignore/Zig.gitignore"),
+    ),
+];
+
+/// The key of a bundled license, accepting SPDX identifiers like `GPL-3.0-or-later` in any case.
+pub fn license_key(name: &str) -> Option<&'static str> {
+    let name = name.to_lowercase();
+    let name = name
+        .trim_end_matches('+')
+        .trim_end_matches("-only")
+        .trim_end_matches("-or-later");
+    LICENSES
+        .iter()
+        .map(|(key, _)| *key)
+        .find(|key| *key == name)
+}
+
+/// The name of a bundled gitignore template in any case, like `rust` for `Rust`.
+pub fn gitignore_name(name: &str) -> Option<&'static str> {
+    GITIGNORES
+        .iter()
+        .map(|(template, _)| *template)
+        .find(|template| template.eq_ignore_ascii_case(name))
+}
+
+/// Where the license and gitignore go.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Destination {
+    Remote,
+    Local,
+    Both,
+}
+
+impl Destination {
+    pub fn remote(self) -> bool {
+        self != Destination::Local
+    }
+
+    pub fn local(self) -> bool {
+        self != Destination::Remote
+    }
+}
+
+/// The bundled license and gitignore picked with `--license` and `--gitignore`.
+pub struct Boilerplate {
+    pub license: Option<&'static str>,
+    pub gitignore: Option<&'static str>,
+    pub destination: Destination,
+    /// Why the names passed that gitpub doesn't bundle, and that aren't a typo of one that it does,
+    /// can't be used. That only matters when gitpub writes the files itself.
+    unbundled: Vec<String>,
+}
+
+impl Boilerplate {
+    pub fn from_matches(matches: &ArgMatches) -> Boilerplate {
+        let license = matches.value_of("license");
+        let gitignore = matches.value_of("gitignore");
+        let unbundled = vec![
+            license.map(|name| validate_license(name.to_string())),
+            gitignore.map(|name| validate_gitignore(name.to_string())),
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(Result::err)
+        .collect();
+        Boilerplate {
+            license: license.and_then(license_key),
+            gitignore: gitignore.and_then(gitignore_name),
+            destination: match matches.value_of("templates_in") {
+                Some("local") => Destination::Local,
+                Some("both") => Destination::Both,
+                _ => Destination::Remote,
+            },
+            unbundled,
+        }
+    }
+
+    /// For providers with templates of their own, fails if a name gitpub doesn't bundle has to be
+    /// written into the working tree, and otherwise warns that it's passed on as it is.
+    pub fn check_unbundled(&self, provider: &str) -> Result<(), String> {
+        if self.destination.local() && !self.unbundled.is_empty() {
+            return Err(self.unbundled.join("\n"));
+        }
+        for reason in &self.unbundled {
+            eprintln!("{} It's passed to {} as it is.", reason, provider);
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.license.is_none() && self.gitignore.is_none()
+    }
+
+    /// The files as their path in the repo and contents, with the license's year and author filled in.
+    pub fn files(&self) -> Vec<(&'static str, String)> {
+        let mut files = Vec::new();
+        if let Some(key) = self.license {
+            let text = LICENSES
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, text)| *text)
+                .unwrap_or_default();
+            let author = git::config("user.name").unwrap_or_else(|| "[fullname]".to_string());
+            let text = text
+                .replace("[year]", &year().to_string())
+                .replace("[fullname]", &author);
+            files.push(("LICENSE", text));
+        }
+        if let Some(name) = self.gitignore {
+            let text = GITIGNORES
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, text)| *text)
+                .unwrap_or_default();
+            files.push((".gitignore", text.to_string()));
+        }
+        files
+    }
+
+    /// Adds the files to the initial commit when they go to the new repo.
+    pub fn add_to(&self, seed: &mut Seed) {
+        if self.destination.remote() {
+            for (path, contents) in self.files() {
+                seed.add(path, contents);
+            }
+        }
+    }
+
+    /// The step writing the files into the working tree when they go there.
+    pub fn local_step(&self) -> Option<Step<'_>> {
+        if self.destination.local() && !self.is_empty() {
+            Some(Step::local("write the license and gitignore", move || {
+                self.write_local()
+            }))
+        } else {
+            None
+        }
+    }
+
+    /// Writes the files into the git toplevel, or the current directory outside of a repository.
+    /// Existing files are never replaced, and each file is written or reported on its own.
+    pub fn write_local(&self) -> Result<(), Box<dyn Error>> {
+        let root = match project::toplevel() {
+            Some(root) => root,
+            None => env::current_dir()?,
+        };
+        let mut failures = Vec::new();
+        for (path, contents) in self.files() {
+            let path = root.join(path);
+            if path.exists() {
+                failures.push(format!(
+                    "`{}` already exists, so it wasn't replaced.",
+                    path.display()
+                ));
+            } else if let Err(e) = fs::write(&path, contents) {
+                failures.push(format!("Couldn't write `{}`: {}", path.display(), e));
+            }
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures.join(" ").into())
+        }
+    }
+}
+
+/// The current year in UTC, from the days since the epoch.
+fn year() -> i64 {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64 / 86_400)
+        .unwrap_or_default();
+    // Howard Hinnant's civil_from_days, counting in 400 year eras that start on March 1st.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    yoe + era * 400 + if month <= 2 { 1 } else { 0 }
+}
+
+/// The closest name to a typo, if it's close enough to be what was meant.
+fn suggest<'a>(name: &str, names: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let name = name.to_lowercase();
+    names
+        .map(|candidate| (distance(&name, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance between two names.
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + if ca == *cb { 0 } else { 1 };
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn unknown(kind: &str, name: &str, names: impl Iterator<Item = &'static str> + Clone) -> String {
+    match suggest(name, names.clone()) {
+        Some(suggestion) => format!(
+            "`{}` isn't a {} gitpub knows. Did you mean `{}`?",
+            name, kind, suggestion
+        ),
+        None => format!(
+            "`{}` isn't a {} gitpub knows. Use one of: {}.",
+            name,
+            kind,
+            names.collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+pub fn validate_license(name: String) -> Result<(), String> {
+    match license_key(&name) {
+        Some(_) => Ok(()),
+        None => Err(unknown(
+            "license",
+            &name,
+            LICENSES.iter().map(|(key, _)| *key),
+        )),
+    }
+}
+
+pub fn validate_gitignore(name: String) -> Result<(), String> {
+    match gitignore_name(&name) {
+        Some(_) => Ok(()),
+        None => Err(unknown(
+            "gitignore template",
+            &name,
+            GITIGNORES.iter().map(|(name, _)| *name),
+        )),
+    }
+}
+
+/// Like `validate_license`, but only rejects typos of a bundled license, for providers that know
+/// licenses gitpub doesn't bundle.
+pub fn validate_license_typo(name: String) -> Result<(), String> {
+    match suggest(&name, LICENSES.iter().map(|(key, _)| *key)) {
+        Some(_) if license_key(&name).is_none() => validate_license(name),
+        _ => Ok(()),
+    }
+}
+
+/// Like `validate_gitignore`, but only rejects typos of a bundled template.
+pub fn validate_gitignore_typo(name: String) -> Result<(), String> {
+    match suggest(&name, GITIGNORES.iter().map(|(name, _)| *name)) {
+        Some(_) if gitignore_name(&name).is_none() => validate_gitignore(name),
+        _ => Ok(()),
+    }
+}
+
+/// The args for providers that only get the files from gitpub, so names are checked against the
+/// bundled ones before anything is sent.
+pub fn args() -> Vec<Arg<'static, 'static>> {
+    let mut args = unchecked_args();
+    args[0] = args[0].clone().validator(validate_license);
+    args[1] = args[1].clone().validator(validate_gitignore);
+    args
+}
+
+/// The args for providers with templates of their own, which know names gitpub doesn't bundle.
+/// Only typos of bundled names are rejected up front, `Boilerplate::check_unbundled` checks the
+/// rest once it's known where the files go.
+pub fn own_template_args() -> Vec<Arg<'static, 'static>> {
+    let mut args = unchecked_args();
+    args[0] = args[0].clone().validator(validate_license_typo);
+    args[1] = args[1].clone().validator(validate_gitignore_typo);
+    args
+}
+
+fn unchecked_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("license")
+            .long("license")
+            .takes_value(true)
+            .help("License template: ex 'mit' or 'mpl-2.0'. View more at https://choosealicense.com/ The year and `git config user.name` are filled in."),
+        Arg::with_name("gitignore")
+            .long("gitignore")
+            .takes_value(true)
+            .help("Language template: ex 'Rust'. View more templates at https://github.com/github/gitignore/"),
+        Arg::with_name("templates_in")
+            .long("templates_in")
+            .takes_value(true)
+            .possible_values(&["remote", "local", "both"])
+            .default_value("remote")
+            .help("Where the --license and --gitignore files go: committed to the new repo, written into the working tree, or both."),
+    ]
+}
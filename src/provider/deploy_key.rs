@@ -0,0 +1,136 @@
+//! Deploy keys, either an existing public key file or a new Ed25519 keypair generated at the given path.
+use clap::{Arg, ArgMatches};
+use crypto_box::aead::OsRng;
+use ssh_key::{Algorithm, LineEnding, PrivateKey};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Where the public half of a deploy key comes from.
+pub enum Source<'a> {
+    /// A new Ed25519 keypair written to this path, with the public key next to it in `<path>.pub`.
+    Generate(&'a str),
+    /// An existing public key file.
+    Existing(&'a str),
+}
+
+pub struct DeployKey<'a> {
+    pub source: Source<'a>,
+    pub read_write: bool,
+}
+
+impl<'a> DeployKey<'a> {
+    pub fn from_matches(matches: &'a ArgMatches<'a>) -> Option<DeployKey<'a>> {
+        let source = match (
+            matches.value_of("deploy_key"),
+            matches.value_of("deploy_public_key"),
+        ) {
+            (Some(path), _) => Source::Generate(path),
+            (None, Some(path)) => Source::Existing(path),
+            (None, None) => return None,
+        };
+        Some(DeployKey {
+            source,
+            read_write: matches.is_present("read_write"),
+        })
+    }
+
+    /// The name the key is listed under, which is the name of its file without `.pub`.
+    pub fn title(&self) -> &'a str {
+        let path = match self.source {
+            Source::Generate(path) | Source::Existing(path) => path,
+        };
+        Path::new(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(path)
+            .trim_end_matches(".pub")
+    }
+
+    /// The public key, generating the keypair first if it's new.
+    pub fn public_key(&self, comment: &str) -> Result<String, Box<dyn Error>> {
+        let public_path = match self.source {
+            Source::Generate(path) => {
+                generate(path, comment)?;
+                format!("{}.pub", path)
+            }
+            Source::Existing(path) => path.to_string(),
+        };
+        let key = fs::read_to_string(&public_path)
+            .map_err(|e| format!("Couldn't read `{}`: {}", public_path, e))?;
+        Ok(key.trim().to_string())
+    }
+}
+
+/// Writes an unencrypted Ed25519 keypair in OpenSSH's format, with the private key readable only
+/// by the current user.
+fn generate(path: &str, comment: &str) -> Result<(), Box<dyn Error>> {
+    let mut key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519)?;
+    key.set_comment(comment);
+    key.write_openssh_file(Path::new(path), LineEnding::LF)
+        .map_err(|e| format!("Couldn't write `{}`: {}", path, e))?;
+    let public_path = format!("{}.pub", path);
+    key.public_key()
+        .write_openssh_file(Path::new(&public_path))
+        .map_err(|e| format!("Couldn't write `{}`: {}", public_path, e))?;
+    Ok(())
+}
+
+/// Checks the keypair can be written before the repo is created, rather than failing afterwards.
+fn validate_new(path: String) -> Result<(), String> {
+    let key = Path::new(&path);
+    if key.exists() || Path::new(&format!("{}.pub", path)).exists() {
+        return Err(format!(
+            "`{}` already exists. Upload an existing key with --deploy_public_key instead.",
+            path
+        ));
+    }
+    let dir = match key.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    match fs::metadata(dir) {
+        Ok(metadata) if !metadata.is_dir() => {
+            Err(format!("`{}` isn't a directory.", dir.display()))
+        }
+        Ok(metadata) if metadata.permissions().readonly() => {
+            Err(format!("`{}` isn't writable.", dir.display()))
+        }
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Couldn't read `{}`: {}", dir.display(), e)),
+    }
+}
+
+fn validate_existing(path: String) -> Result<(), String> {
+    match fs::read_to_string(&path) {
+        Ok(key) if key.starts_with("ssh-") || key.starts_with("ecdsa-") => Ok(()),
+        Ok(_) => Err(format!("`{}` isn't an ssh public key.", path)),
+        Err(e) => Err(format!("Couldn't read `{}`: {}", path, e)),
+    }
+}
+
+pub fn args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("deploy_key")
+            .long("deploy_key")
+            .takes_value(true)
+            .value_name("path")
+            .validator(validate_new)
+            .group("deploy_keys")
+            .help("Generates an Ed25519 keypair at this path after creation, readable only by you, and uploads the public half as a deploy key."),
+        Arg::with_name("deploy_public_key")
+            .long("deploy_public_key")
+            .takes_value(true)
+            .value_name("path")
+            .validator(validate_existing)
+            .group("deploy_keys")
+            .help("Uploads an existing public key file as a deploy key after creation."),
+    ]
+}
+
+pub fn read_write_arg() -> Arg<'static, 'static> {
+    Arg::with_name("read_write")
+        .long("read_write")
+        .requires("deploy_keys")
+        .help("Lets the deploy key push. Deploy keys are read-only otherwise.")
+}
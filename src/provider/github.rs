@@ -1,13 +1,30 @@
-use super::Provider;
-use clap::{App, Arg, ArgMatches, SubCommand};
+use super::access::{self, Grant, Permission};
+use super::boilerplate::{self, Boilerplate};
+use super::deploy_key::{self, DeployKey};
+use super::label::{self, Labels};
+use super::protect::{self, Protection};
+use super::seed::{self, Seed};
+use super::variable::{self, Variable};
+use super::webhook::{self, Webhook};
+use super::{encode, slug, LikeMatches, Provider, RepoSummary, RepoUrls, Request, Settings, Step};
+use crate::action;
+use crate::api::Api;
+use crate::project::{self, Project};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use crypto_box::aead::OsRng;
+use crypto_box::PublicKey;
+use reqwest::{Method, StatusCode};
 use serde::Serialize;
-use serde_json::to_string;
+use serde_json::{json, to_string, Value};
+use std::error::Error;
 
 #[derive(Serialize)]
 pub struct GithubArgs<'a> {
     name: &'a str,
     #[serde(skip_serializing)]
     token: &'a str,
+    #[serde(skip_serializing)]
+    api_url: &'a str,
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<&'a str>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -16,8 +33,6 @@ pub struct GithubArgs<'a> {
     has_issues: bool,
     has_projects: bool,
     has_wiki: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    team_id: Option<u32>,
     auto_init: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     gitignore_template: Option<&'a str>,
@@ -26,22 +41,258 @@ pub struct GithubArgs<'a> {
     allow_squash_merge: bool,
     allow_merge_commit: bool,
     allow_rebase_merge: bool,
+    is_template: bool,
     org: Option<&'a str>,
+    #[serde(skip_serializing)]
+    template: Option<&'a str>,
+    #[serde(skip_serializing)]
+    include_all_branches: bool,
+    #[serde(skip_serializing)]
+    topics: Vec<String>,
+    #[serde(skip_serializing)]
+    protection: Protection<'a>,
+    #[serde(skip_serializing)]
+    collaborators: Vec<Grant<'a>>,
+    #[serde(skip_serializing)]
+    teams: Vec<Grant<'a>>,
+    #[serde(skip_serializing)]
+    webhooks: Vec<Webhook<'a>>,
+    #[serde(skip_serializing)]
+    deploy_key: Option<DeployKey<'a>>,
+    #[serde(skip_serializing)]
+    variables: Vec<Variable<'a>>,
+    #[serde(skip_serializing)]
+    labels: Labels,
+    #[serde(skip_serializing)]
+    seed: Seed<'a>,
+    #[serde(skip_serializing)]
+    boilerplate: Boilerplate,
 }
 
-const ENDPOINT: &str = "https://api.github.com/user/repos";
-const ORG_ENDPOINT: &str = "https://api.github.com/orgs/{}/repos";
+/// Encrypts a secret for actions with a libsodium sealed box to the repo's base64 public key.
+fn seal(public_key: &str, secret: &str) -> Result<String, Box<dyn Error>> {
+    let public_key = PublicKey::from_slice(&base64::decode(public_key)?)
+        .map_err(|_| "The repo's public key isn't a 32 byte key.")?;
+    let sealed = public_key
+        .seal(&mut OsRng, secret.as_bytes())
+        .map_err(|_| "Couldn't encrypt the secret.")?;
+    Ok(base64::encode(&sealed))
+}
+
+/// Github's name for a permission.
+fn permission(permission: Permission) -> &'static str {
+    match permission {
+        Permission::Read => "pull",
+        Permission::Triage => "triage",
+        Permission::Write => "push",
+        Permission::Maintain => "maintain",
+        Permission::Admin => "admin",
+    }
+}
+
+impl<'a> GithubArgs<'a> {
+    /// Whether the repo starts with a commit, so its default branch exists straight away.
+    fn initialized(&self) -> bool {
+        self.initialized_on_create() || self.template.is_some()
+    }
+
+    /// Whether github commits to the repo before responding, unlike generating it from a template,
+    /// which happens in the background.
+    fn initialized_on_create(&self) -> bool {
+        self.auto_init || self.gitignore_template.is_some() || self.license_template.is_some()
+    }
+
+    /// Commits the seed files on the default branch. An empty repo has no branch to build a
+    /// commit on, so the first file is added through the contents api to create it.
+    fn seed(&self, api: &Api, repo: &str, branch: &str) -> Result<(), Box<dyn Error>> {
+        let mut files = self.seed.read()?;
+        if !self.initialized() && !files.is_empty() {
+            let first = files.remove(0);
+            let body =
+                json!({ "message": seed::MESSAGE, "content": base64::encode(&first.contents) });
+            let path = first
+                .path
+                .split('/')
+                .map(encode)
+                .collect::<Vec<_>>()
+                .join("/");
+            api.request(
+                Method::PUT,
+                &format!("{}/contents/{}", repo, path),
+                Some(&body),
+            )?
+            .into_json()?;
+        }
+        if files.is_empty() {
+            return Ok(());
+        }
+
+        let head = api
+            .get(&format!("{}/git/ref/heads/{}", repo, branch))?
+            .into_json()?;
+        let parent = &head["object"]["sha"];
+        let parent_commit = api
+            .get(&format!(
+                "{}/git/commits/{}",
+                repo,
+                parent.as_str().unwrap_or_default()
+            ))?
+            .into_json()?;
+
+        let mut tree = Vec::new();
+        for file in &files {
+            let blob = json!({ "content": base64::encode(&file.contents), "encoding": "base64" });
+            let blob = api
+                .request(Method::POST, &format!("{}/git/blobs", repo), Some(&blob))?
+                .into_json()?;
+            tree.push(json!({
+                "path": file.path,
+                "mode": if file.executable { "100755" } else { "100644" },
+                "type": "blob",
+                "sha": blob["sha"],
+            }));
+        }
+        let tree = json!({ "base_tree": parent_commit["tree"]["sha"], "tree": tree });
+        let tree = api
+            .request(Method::POST, &format!("{}/git/trees", repo), Some(&tree))?
+            .into_json()?;
+        let commit = json!({ "message": seed::MESSAGE, "tree": tree["sha"], "parents": [parent] });
+        let commit = api
+            .request(
+                Method::POST,
+                &format!("{}/git/commits", repo),
+                Some(&commit),
+            )?
+            .into_json()?;
+        api.request(
+            Method::PATCH,
+            &format!("{}/git/refs/heads/{}", repo, branch),
+            Some(&json!({ "sha": commit["sha"] })),
+        )?
+        .into_json()?;
+        Ok(())
+    }
+
+    /// Classic protection for a branch that already exists.
+    fn branch_protection(&self) -> Value {
+        let protection = &self.protection;
+        json!({
+            "required_status_checks": if protection.status_checks.is_empty() {
+                Value::Null
+            } else {
+                json!({ "strict": true, "contexts": protection.status_checks })
+            },
+            "enforce_admins": protection.enforce_admins,
+            "required_pull_request_reviews": if protection.required_reviews == 0 && !protection.dismiss_stale_reviews {
+                Value::Null
+            } else {
+                json!({
+                    "dismiss_stale_reviews": protection.dismiss_stale_reviews,
+                    "required_approving_review_count": protection.required_reviews,
+                })
+            },
+            "restrictions": null,
+            "required_linear_history": protection.linear_history,
+        })
+    }
+
+    /// A ruleset protecting a branch, which unlike classic protection can target a branch that doesn't exist yet.
+    fn ruleset(&self, branch: &str) -> Value {
+        let protection = &self.protection;
+        let mut rules = vec![
+            json!({ "type": "deletion" }),
+            json!({ "type": "non_fast_forward" }),
+        ];
+        if protection.required_reviews > 0 || protection.dismiss_stale_reviews {
+            rules.push(json!({
+                "type": "pull_request",
+                "parameters": {
+                    "required_approving_review_count": protection.required_reviews,
+                    "dismiss_stale_reviews_on_push": protection.dismiss_stale_reviews,
+                    "require_code_owner_review": false,
+                    "require_last_push_approval": false,
+                    "required_review_thread_resolution": false,
+                },
+            }));
+        }
+        if !protection.status_checks.is_empty() {
+            let checks = protection
+                .status_checks
+                .iter()
+                .map(|context| json!({ "context": context }))
+                .collect::<Vec<_>>();
+            rules.push(json!({
+                "type": "required_status_checks",
+                "parameters": {
+                    "strict_required_status_checks_policy": true,
+                    "required_status_checks": checks,
+                },
+            }));
+        }
+        if protection.linear_history {
+            rules.push(json!({ "type": "required_linear_history" }));
+        }
+        // Classic protection lets admins through unless it's enforced, so do the same by letting the admin role bypass.
+        let bypass_actors = if protection.enforce_admins {
+            json!([])
+        } else {
+            json!([{ "actor_id": 5, "actor_type": "RepositoryRole", "bypass_mode": "always" }])
+        };
+
+        json!({
+            "name": format!("Protect {}", branch),
+            "target": "branch",
+            "enforcement": "active",
+            "conditions": { "ref_name": { "include": [format!("refs/heads/{}", branch)], "exclude": [] } },
+            "bypass_actors": bypass_actors,
+            "rules": rules,
+        })
+    }
+}
+
+/// The payload for generating a repo from a template, which takes far fewer options than creating one.
+#[derive(Serialize)]
+struct GenerateArgs<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<&'a str>,
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    private: bool,
+    include_all_branches: bool,
+}
+
+const API_URL: &str = "https://api.github.com";
+const ENDPOINT: &str = "/user/repos";
+const ORG_ENDPOINT: &str = "/orgs/{}/repos";
+const GENERATE_ENDPOINT: &str = "/repos/{}/generate";
 
 impl<'a> Provider for GithubArgs<'a> {
     fn payload(&self) -> String {
-        to_string(&self).unwrap()
+        match self.template {
+            Some(_) => to_string(&GenerateArgs {
+                owner: self.org,
+                name: self.name,
+                description: self.description,
+                private: self.private,
+                include_all_branches: self.include_all_branches,
+            })
+            .unwrap(),
+            None => to_string(&self).unwrap(),
+        }
     }
 
     fn endpoint(&self) -> String {
-        if let Some(org) = &self.org {
-            ORG_ENDPOINT.replace("{}", &org)
+        if let Some(template) = &self.template {
+            format!(
+                "{}{}",
+                self.api_url,
+                GENERATE_ENDPOINT.replace("{}", template)
+            )
+        } else if let Some(org) = &self.org {
+            format!("{}{}", self.api_url, ORG_ENDPOINT.replace("{}", org))
         } else {
-            ENDPOINT.to_string()
+            format!("{}{}", self.api_url, ENDPOINT)
         }
     }
 
@@ -61,6 +312,336 @@ impl<'a> Provider for GithubArgs<'a> {
         "Authorization".to_string()
     }
 
+    fn name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn slug(&self) -> Result<String, String> {
+        slug::github(self.name)
+    }
+
+    fn check_options(&self) -> Result<(), String> {
+        self.boilerplate.check_unbundled("github")
+    }
+
+    fn api_url(&self) -> String {
+        self.api_url.to_string()
+    }
+
+    fn repo_path(&self, api: &Api) -> Result<String, Box<dyn Error>> {
+        let owner = match self.org {
+            Some(org) => org.to_string(),
+            None => login(api)?,
+        };
+        Ok(format!("/repos/{}/{}", owner, self.slug()?))
+    }
+
+    fn repo_urls(&self, repo: &Value) -> RepoUrls {
+        RepoUrls {
+            web: repo["html_url"].as_str().map(str::to_string),
+            https: repo["clone_url"].as_str().map(str::to_string),
+            ssh: repo["ssh_url"].as_str().map(str::to_string),
+        }
+    }
+
+    fn list_path(&self) -> String {
+        match self.org {
+            Some(org) => format!("/orgs/{}/repos?per_page=100", org),
+            None => "/user/repos?per_page=100".to_string(),
+        }
+    }
+
+    fn repo_summary(&self, repo: &Value) -> RepoSummary {
+        RepoSummary {
+            name: repo["full_name"].as_str().unwrap_or_default().to_string(),
+            private: repo["private"].as_bool().unwrap_or_default(),
+            archived: repo["archived"].as_bool().unwrap_or_default(),
+            description: repo["description"].as_str().unwrap_or_default().to_string(),
+            url: repo["html_url"].as_str().unwrap_or_default().to_string(),
+            default_branch: repo["default_branch"].as_str().map(str::to_string),
+        }
+    }
+
+    fn full_repo_path(&self, full_name: &str) -> String {
+        format!("/repos/{}", full_name)
+    }
+
+    fn settings(&self, repo: &Value) -> Settings {
+        let mut settings = Settings::default();
+        settings.value("name", repo["name"].as_str());
+        if repo["owner"]["type"] == "Organization" {
+            settings.value("org", repo["owner"]["login"].as_str());
+        }
+        settings.value("description", repo["description"].as_str());
+        settings.value("homepage", repo["homepage"].as_str());
+        settings.flag("private", repo["private"] == true);
+        settings.flag("disable_issues", repo["has_issues"] == false);
+        settings.flag("disable_projects", repo["has_projects"] == false);
+        settings.flag("disable_wiki", repo["has_wiki"] == false);
+        settings.flag("disable_squash", repo["allow_squash_merge"] == false);
+        settings.flag("disable_merge", repo["allow_merge_commit"] == false);
+        settings.flag("disable_rebase", repo["allow_rebase_merge"] == false);
+        settings.flag("is_template", repo["is_template"] == true);
+        let topics = repo["topics"]
+            .as_array()
+            .map(|topics| {
+                topics
+                    .iter()
+                    .filter_map(|t| t.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        settings.values("topic", topics);
+        settings
+    }
+
+    fn archive_request(&self, full_name: &str, archived: bool) -> Result<Request, Box<dyn Error>> {
+        Ok((
+            Method::PATCH,
+            self.full_repo_path(full_name),
+            Some(json!({ "archived": archived })),
+        ))
+    }
+
+    fn post_create(&self, created: &RepoSummary) -> Vec<Step<'_>> {
+        let full_name = created.name.as_str();
+        let mut steps = Vec::new();
+        // Files go in first so protection and webhooks don't apply to the initial commit.
+        if !self.seed.is_empty() {
+            let repo = self.full_repo_path(full_name);
+            let branch = created
+                .default_branch
+                .clone()
+                .unwrap_or_else(|| "main".to_string());
+            steps.push(Step::with_api("add the seed files", move |api| {
+                self.seed(api, &repo, &branch)
+            }));
+        }
+        // Topics can't be passed when creating a repo, they're replaced as a whole afterwards.
+        if !self.topics.is_empty() {
+            steps.push(Step::new(
+                "set the topics",
+                (
+                    Method::PUT,
+                    format!("{}/topics", self.full_repo_path(full_name)),
+                    Some(json!({ "names": self.topics })),
+                ),
+            ));
+        }
+        for branch in &self.protection.branches {
+            let repo = self.full_repo_path(full_name);
+            // Classic protection needs the branch to exist, which is only certain for the default
+            // branch of a repo github committed to while creating it.
+            let exists =
+                self.initialized_on_create() && created.default_branch.as_deref() == Some(branch);
+            steps.push(Step::with_api(
+                format!("protect `{}`", branch),
+                move |api| {
+                    if exists {
+                        let response = api.request(
+                            Method::PUT,
+                            &format!("{}/branches/{}/protection", repo, branch),
+                            Some(&self.branch_protection()),
+                        )?;
+                        if response.status != StatusCode::NOT_FOUND {
+                            return response.into_json().map(|_| ());
+                        }
+                    }
+                    api.request(
+                        Method::POST,
+                        &format!("{}/rulesets", repo),
+                        Some(&self.ruleset(branch)),
+                    )?
+                    .into_json()?;
+                    Ok(())
+                },
+            ));
+        }
+        for collaborator in &self.collaborators {
+            steps.push(Step::new(
+                format!("add `{}` as a collaborator", collaborator.name),
+                (
+                    Method::PUT,
+                    format!(
+                        "{}/collaborators/{}",
+                        self.full_repo_path(full_name),
+                        collaborator.name
+                    ),
+                    Some(json!({ "permission": permission(collaborator.permission) })),
+                ),
+            ));
+        }
+        // Teams are only accepted with --org.
+        if let Some(org) = self.org {
+            for team in &self.teams {
+                steps.push(Step::new(
+                    format!("give the `{}` team access", team.name),
+                    (
+                        Method::PUT,
+                        format!("/orgs/{}/teams/{}/repos/{}", org, team.name, full_name),
+                        Some(json!({ "permission": permission(team.permission) })),
+                    ),
+                ));
+            }
+        }
+        for hook in &self.webhooks {
+            let mut config = json!({ "url": hook.url, "content_type": "json" });
+            if let Some(secret) = &hook.secret {
+                config["secret"] = json!(secret);
+            }
+            steps.push(Step::new(
+                format!("add the `{}` webhook", hook.url),
+                (
+                    Method::POST,
+                    format!("{}/hooks", self.full_repo_path(full_name)),
+                    Some(json!({
+                        "name": "web",
+                        "active": true,
+                        "events": hook.events,
+                        "config": config,
+                    })),
+                ),
+            ));
+        }
+        if let Some(key) = &self.deploy_key {
+            let repo = self.full_repo_path(full_name);
+            let comment = full_name.to_string();
+            steps.push(Step::lookup(
+                format!("add the `{}` deploy key", key.title()),
+                move |_| {
+                    let public_key = key.public_key(&comment)?;
+                    Ok((
+                        Method::POST,
+                        format!("{}/keys", repo),
+                        Some(json!({ "title": key.title(), "key": public_key, "read_only": !key.read_write })),
+                    ))
+                },
+            ));
+        }
+        for variable in &self.variables {
+            let repo = self.full_repo_path(full_name);
+            if !variable.secret {
+                steps.push(Step::lookup(
+                    format!("set the `{}` variable", variable.name),
+                    move |_| {
+                        Ok((
+                            Method::POST,
+                            format!("{}/actions/variables", repo),
+                            Some(json!({ "name": variable.name, "value": variable.value()? })),
+                        ))
+                    },
+                ));
+                continue;
+            }
+            steps.push(Step::lookup(
+                format!("set the `{}` secret", variable.name),
+                move |api| {
+                    let value = variable.value()?;
+                    let key = api
+                        .get(&format!("{}/actions/secrets/public-key", repo))?
+                        .into_json()?;
+                    let public_key = key["key"]
+                        .as_str()
+                        .ok_or("Github didn't return the repo's public key.")?;
+                    Ok((
+                        Method::PUT,
+                        format!("{}/actions/secrets/{}", repo, variable.name),
+                        Some(json!({
+                            "encrypted_value": seal(public_key, &value)?,
+                            "key_id": key["key_id"],
+                        })),
+                    ))
+                },
+            ));
+        }
+        let repo = self.full_repo_path(full_name);
+        if self.labels.replace_defaults {
+            let repo = repo.clone();
+            steps.push(Step::lookup_all("remove the default labels", move |api| {
+                let existing = api
+                    .get(&format!("{}/labels?per_page=100", repo))?
+                    .into_json()?;
+                Ok(existing
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|label| label["name"].as_str())
+                    .filter(|name| !self.labels.contains(name))
+                    .map(|name| {
+                        (
+                            Method::DELETE,
+                            format!("{}/labels/{}", repo, encode(name)),
+                            None,
+                        )
+                    })
+                    .collect())
+            }));
+        }
+        for label in &self.labels.labels {
+            let repo = repo.clone();
+            steps.push(Step::lookup(
+                format!("set the `{}` label", label.name),
+                move |api| {
+                    let path = format!("{}/labels/{}", repo, encode(&label.name));
+                    let mut payload =
+                        json!({ "color": label.color, "description": label.description });
+                    // Labels the repo starts with, like `bug`, are updated instead.
+                    if api.get(&path)?.is_success() {
+                        payload["new_name"] = json!(label.name);
+                        Ok((Method::PATCH, path, Some(payload)))
+                    } else {
+                        payload["name"] = json!(label.name);
+                        Ok((Method::POST, format!("{}/labels", repo), Some(payload)))
+                    }
+                },
+            ));
+        }
+        steps.extend(self.boilerplate.local_step());
+        steps
+    }
+
+    fn rename_request(&self, full_name: &str, new_name: &str) -> Request {
+        (
+            Method::PATCH,
+            self.full_repo_path(full_name),
+            Some(json!({ "name": new_name })),
+        )
+    }
+
+    fn transfer_request(
+        &self,
+        full_name: &str,
+        new_owner: &str,
+    ) -> Result<Request, Box<dyn Error>> {
+        Ok((
+            Method::POST,
+            format!("{}/transfer", self.full_repo_path(full_name)),
+            Some(json!({ "new_owner": new_owner })),
+        ))
+    }
+
+    fn fork_payload(&self) -> Value {
+        match self.org {
+            Some(org) => json!({ "organization": org }),
+            None => json!({}),
+        }
+    }
+
+    fn fork_ready(&self, api: &Api, fork: &Value) -> Result<bool, Box<dyn Error>> {
+        // Forks are created in the background, and the commits are only served once the copy is done.
+        let path = format!(
+            "{}/commits?per_page=1",
+            self.full_repo_path(fork["full_name"].as_str().unwrap_or_default())
+        );
+        let response = api.get(&path)?;
+        match response.status {
+            // An empty repository has no commits to wait for.
+            StatusCode::CONFLICT => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            _ => response.into_json().map(|_| true),
+        }
+    }
+
     fn ssh_url(&self, headers: &reqwest::header::HeaderMap) -> Option<String> {
         headers
             .get("location")
@@ -76,28 +657,106 @@ impl<'a> Provider for GithubArgs<'a> {
     }
 }
 
+/// The login of the user the token belongs to.
+fn login(api: &Api) -> Result<String, Box<dyn Error>> {
+    let user = api.get("/user")?.into_json()?;
+    user["login"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| "Github didn't respond with the login of the token's user.".into())
+}
+
+fn name_arg() -> Arg<'static, 'static> {
+    Arg::with_name("name")
+        .short("n")
+        .long("name")
+        .help("The name of the new repository. Defaults to the name of the git toplevel directory.")
+        .takes_value(true)
+}
+
+fn token_arg() -> Arg<'static, 'static> {
+    Arg::with_name("token")
+        .short("t")
+        .long("token")
+        .help("A personal access token. Alternatively read from GITHUB_REPO_TOKEN env variable.")
+        .env("GITHUB_REPO_TOKEN")
+        .takes_value(true)
+        .required(true)
+}
+
+fn api_url_arg() -> Arg<'static, 'static> {
+    Arg::with_name("api_url")
+        .long("api_url")
+        .help("The root of the github api, useful for self-hosted instances. Alternatively read from GITHUB_API_URL env variable.")
+        .env("GITHUB_API_URL")
+        .takes_value(true)
+        .default_value(API_URL)
+}
+
+fn org_arg() -> Arg<'static, 'static> {
+    Arg::with_name("org")
+        .long("org")
+        .takes_value(true)
+        .help("Creates the repo under an organization. Requires you have CREATE REPO permissions in that org.")
+}
+
 pub fn subcommand() -> App<'static, 'static> {
     SubCommand::with_name("github")
         .version(env!("CARGO_PKG_VERSION"))
         .about("Create a repo on github.")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Checks whether a repo with this name already exists.")
+                .arg(name_arg())
+                .arg(token_arg())
+                .arg(api_url_arg())
+                .arg(org_arg())
+        )
+        .subcommand(
+            action::list_subcommand()
+                .arg(token_arg())
+                .arg(api_url_arg())
+                .arg(org_arg().help("Lists the repos of an organization instead of your own."))
+        )
+        .subcommand(
+            action::show_subcommand()
+                .arg(token_arg())
+                .arg(api_url_arg())
+        )
+        .subcommand(action::archive_subcommand("archive").arg(token_arg()).arg(api_url_arg()))
+        .subcommand(action::archive_subcommand("unarchive").arg(token_arg()).arg(api_url_arg()))
+        .subcommand(
+            action::rename_subcommand()
+                .arg(token_arg())
+                .arg(api_url_arg())
+        )
+        .subcommand(
+            action::transfer_subcommand()
+                .arg(token_arg())
+                .arg(api_url_arg())
+        )
+        .subcommand(
+            action::fork_subcommand()
+                .arg(token_arg())
+                .arg(api_url_arg())
+                .arg(org_arg().help("Forks into an organization instead of your account."))
+        )
+        .arg(name_arg())
+        .arg(token_arg())
+        .arg(api_url_arg())
+        .arg(action::like_arg())
+        .args(&action::unlike_args(&[
+            "private",
+            "disable_issues",
+            "disable_projects",
+            "disable_wiki",
+            "disable_squash",
+            "disable_merge",
+            "disable_rebase",
+            "is_template",
+        ]))
         .arg(
-            Arg::with_name("name")
-                .short("n")
-                .long("name")
-                .help("The name of the new repository.")
-                .takes_value(true)
-                .required(true)
-        ).arg(
-            Arg::with_name("token")
-                .short("t")
-                .long("token")
-                .help(
-                    "A personal access token. Alternatively read from GITHUB_REPO_TOKEN env variable.",
-                )
-                .env("GITHUB_REPO_TOKEN")
-                .takes_value(true)
-                .required(true)
-        ).arg(
             Arg::with_name("description")
                 .short("d")
                 .long("description")
@@ -127,27 +786,25 @@ pub fn subcommand() -> App<'static, 'static> {
                 .short("w")
                 .long("disable_wiki")
                 .help("Disables wikis for this repo. Defaults to false.")
-        ).arg(
-            Arg::with_name("team")
-                .long("team")
-                .help("ID of the team that has access to this repo. Only valid when using --org")
+        ).arg(webhook::arg())
+        .args(&deploy_key::args())
+        .arg(deploy_key::read_write_arg())
+        .args(&seed::args())
+        .args(&label::args())
+        .arg(variable::variable_arg().help("Sets an actions variable after creation, ex `--variable REGISTRY=ghcr.io`."))
+        .arg(variable::secret_arg().help("Sets an encrypted actions secret after creation, read from a file with `--secret NAME=@file` or from an environment variable with `--secret NAME=env:VAR`."))
+        .arg(access::collaborator_arg())
+        .arg(
+            access::team_arg()
+                .help("Gives a team of the --org access after creation by its slug, ex `--team platform:maintain`. Takes the same permissions as --collaborator.")
                 .requires("org")
         ).arg(
             Arg::with_name("readme")
                 .short("r")
                 .long("initialize_with_readme")
                 .help("Creates an initial commit with empty README.md. Defaults to false.")
-        ).arg(
-            Arg::with_name("gitignore")
-                .long("gitignore")
-                .takes_value(true)
-                .help("Language template: ex 'Rust'. View more templates at https://github.com/github/gitignore/")
-        ).arg(
-            Arg::with_name("license")
-                .long("license")
-                .takes_value(true)
-                .help("License template: ex 'mit' or 'mpl-2.0'. View more at https://choosealicense.com/")
-        ).arg(
+        ).args(&boilerplate::own_template_args())
+        .arg(
             Arg::with_name("disable_squash")
                 .long("disable_squash")
                 .help("Disables squash-merging for this repo.")
@@ -160,33 +817,109 @@ pub fn subcommand() -> App<'static, 'static> {
                 .long("disable_rebase")
                 .help("Disables rebase-merging for this repo.")
         ).arg(
-            Arg::with_name("org")
-                .long("disable_org")
-                .help("Creates the repo under an organization. Requires you have CREATE REPO permissions in that org.")
-        )
+            Arg::with_name("is_template")
+                .long("is_template")
+                .help("Marks the new repo as a template others can be generated from.")
+        ).arg(
+            Arg::with_name("template")
+                .long("template")
+                .takes_value(true)
+                .value_name("owner/repo")
+                .help("Generates the repo from a template repo. Only the name, description, privacy and --org are passed on, the rest comes from the template.")
+                .conflicts_with_all(&["readme", "gitignore", "license", "is_template", "like"])
+        ).arg(
+            Arg::with_name("include_all_branches")
+                .long("include_all_branches")
+                .help("Copies every branch of the template instead of only the default branch.")
+                .requires("template")
+        ).arg(
+            Arg::with_name("topic")
+                .long("topic")
+                .takes_value(true)
+                .multiple(true)
+                .help("Topics to make the repo discoverable, ex `--topic rust --topic cli`. Defaults to the manifest's keywords.")
+        ).args(&protect::args())
+        .arg(protect::linear_history_arg())
+        .arg(protect::enforce_admins_arg())
+        .arg(org_arg())
 }
 
-pub fn from_matches<'a>(matches: &'a ArgMatches) -> GithubArgs<'a> {
+pub fn from_matches<'a>(
+    matches: &'a ArgMatches<'a>,
+    project: &'a Project,
+    like: &'a Settings,
+) -> GithubArgs<'a> {
+    let settings = LikeMatches::new(matches, like);
+    let auto_init = matches.is_present("readme");
+    let boilerplate = Boilerplate::from_matches(matches);
     GithubArgs {
-        name: matches
-            .value_of("name")
-            .expect("This property is required. This statement should be unreachable."),
+        name: project::name(matches, project),
         token: matches
             .value_of("token")
             .expect("This property is required. This error should be unreachable."),
-        description: matches.value_of("description"),
-        homepage: matches.value_of("homepage"),
-        private: matches.is_present("private"),
-        has_issues: !matches.is_present("disable_issues"),
-        has_projects: !matches.is_present("disable_projects"),
-        has_wiki: !matches.is_present("disable_wiki"),
-        team_id: matches.value_of("team").and_then(|x| x.parse::<u32>().ok()),
-        auto_init: matches.is_present("readme"),
-        gitignore_template: matches.value_of("gitignore"),
-        license_template: matches.value_of("license"),
-        allow_squash_merge: !matches.is_present("disable_squash"),
-        allow_merge_commit: !matches.is_present("disable_merge"),
-        allow_rebase_merge: !matches.is_present("disable_rebase"),
+        api_url: matches.value_of("api_url").unwrap_or(API_URL),
+        description: matches
+            .value_of("description")
+            .or(project.description.as_deref())
+            .or_else(|| like.value_of("description")),
+        homepage: matches
+            .value_of("homepage")
+            .or(project.homepage.as_deref())
+            .or_else(|| like.value_of("homepage")),
+        private: settings.is_present("private"),
+        has_issues: !settings.is_present("disable_issues"),
+        has_projects: !settings.is_present("disable_projects"),
+        has_wiki: !settings.is_present("disable_wiki"),
+        auto_init,
+        // Github fills in its own templates when they're committed to the new repo, and knows more
+        // of them than gitpub bundles.
+        gitignore_template: boilerplate
+            .gitignore
+            .or_else(|| matches.value_of("gitignore"))
+            .filter(|_| boilerplate.destination.remote()),
+        // A license from the manifest would add a commit the local history doesn't have, so only use it when one is made anyway.
+        license_template: boilerplate
+            .license
+            .or_else(|| matches.value_of("license"))
+            .filter(|_| boilerplate.destination.remote())
+            .or_else(|| {
+                let license = project.license.as_deref().filter(|_| auto_init)?;
+                Some(boilerplate::license_key(license).unwrap_or(license))
+            }),
+        allow_squash_merge: !settings.is_present("disable_squash"),
+        allow_merge_commit: !settings.is_present("disable_merge"),
+        allow_rebase_merge: !settings.is_present("disable_rebase"),
+        is_template: settings.is_present("is_template"),
         org: matches.value_of("org"),
+        template: matches.value_of("template"),
+        include_all_branches: matches.is_present("include_all_branches"),
+        // Github only accepts lowercase topics.
+        topics: matches
+            .values_of("topic")
+            .map(|x| x.collect::<Vec<_>>())
+            .or_else(|| {
+                Some(
+                    project
+                        .keywords
+                        .iter()
+                        .map(String::as_str)
+                        .collect::<Vec<_>>(),
+                )
+                .filter(|k| !k.is_empty())
+            })
+            .or_else(|| like.values_of("topic"))
+            .unwrap_or_default()
+            .iter()
+            .map(|topic| topic.to_lowercase())
+            .collect(),
+        protection: Protection::from_matches(matches),
+        collaborators: Grant::from_matches(matches, "collaborator"),
+        teams: Grant::from_matches(matches, "team"),
+        webhooks: Webhook::from_matches(matches),
+        deploy_key: DeployKey::from_matches(matches),
+        variables: Variable::from_matches(matches),
+        labels: Labels::from_matches(matches),
+        seed: Seed::from_matches(matches),
+        boilerplate,
     }
 }
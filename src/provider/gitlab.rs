@@ -1,12 +1,29 @@
-use super::Provider;
-use clap::{App, Arg, ArgMatches, SubCommand};
+use super::access::{self, Grant, Permission};
+use super::boilerplate::{self, Boilerplate};
+use super::deploy_key::{self, DeployKey};
+use super::label::{self, Labels};
+use super::protect::{self, Protection};
+use super::seed::{self, Seed};
+use super::variable::{self, Variable};
+use super::webhook::{self, Webhook};
+use super::{encode, slug, LikeMatches, Provider, RepoSummary, RepoUrls, Request, Settings, Step};
+use crate::action;
+use crate::api::{Api, Response};
+use crate::project::{self, Project};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use reqwest::Method;
 use serde::Serialize;
-use serde_json::to_string;
+use serde_json::{json, to_string, to_value, Value};
+use std::error::Error;
 
 #[derive(Serialize)]
 pub struct GitlabArgs<'a> {
     #[serde(skip_serializing)]
     token: &'a str,
+    #[serde(skip_serializing)]
+    api_url: &'a str,
+    #[serde(skip_serializing)]
+    group: Option<&'a str>,
     #[serde(skip_serializing_if = "Option::is_none")]
     name: Option<&'a str>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -35,7 +52,8 @@ pub struct GitlabArgs<'a> {
     merge_method: Option<&'a str>,
     lfs_enabled: bool,
     request_access_enabled: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    // Gitlab renamed the tag list to topics.
+    #[serde(rename = "topics", skip_serializing_if = "Option::is_none")]
     tag_list: Option<Vec<&'a str>>,
     printing_merge_request_link_enabled: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -47,30 +65,91 @@ pub struct GitlabArgs<'a> {
     mirror: bool,
     mirror_trigger_builds: bool,
     initialize_with_readme: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    template_name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    template_project_id: Option<u32>,
+    use_custom_template: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group_with_project_templates_id: Option<u32>,
+    #[serde(skip_serializing)]
+    protection: Protection<'a>,
+    #[serde(skip_serializing)]
+    members: Vec<Grant<'a>>,
+    #[serde(skip_serializing)]
+    groups: Vec<Grant<'a>>,
+    #[serde(skip_serializing)]
+    webhooks: Vec<Webhook<'a>>,
+    #[serde(skip_serializing)]
+    deploy_key: Option<DeployKey<'a>>,
+    #[serde(skip_serializing)]
+    variables: Vec<Variable<'a>>,
+    #[serde(skip_serializing)]
+    labels: Labels,
+    #[serde(skip_serializing)]
+    seed: Seed<'a>,
+    #[serde(skip_serializing)]
+    boilerplate: Boilerplate,
+    #[serde(skip_serializing)]
+    protected_variables: bool,
+    #[serde(skip_serializing)]
+    masked_variables: bool,
 }
 //TODO: The rest of the options in https://docs.gitlab.com/ee/api/projects.html#create-project
 
 impl<'a> GitlabArgs<'a> {
     fn project_name(&self) -> String {
-        if let Some(ref p) = self.path {
-            p.to_string()
-        } else if let Some(ref n) = self.name {
-            n.to_string()
-        } else {
-            unreachable!()
-        }
+        self.slug()
+            .unwrap_or_else(|_| self.path.or(self.name).unwrap_or_default().to_string())
     }
 }
 
-const ENDPOINT: &str = "https://gitlab.com/api/v4/projects";
+const API_URL: &str = "https://gitlab.com/api/v4";
+const ENDPOINT: &str = "/projects";
+/// Gitlab's built-in project templates at the time of writing. Newer instances may have more.
+const BUILT_IN_TEMPLATES: &[&str] = &[
+    "rails",
+    "spring",
+    "express",
+    "iosswift",
+    "dotnetcore",
+    "android",
+    "gomicro",
+    "gatsby",
+    "hugo",
+    "jekyll",
+    "plainhtml",
+    "gitbook",
+    "hexo",
+    "gitpod_spring_petclinic",
+    "nfhugo",
+    "nfjekyll",
+    "nfplainhtml",
+    "nfgitbook",
+    "nfhexo",
+    "salesforcedx",
+    "serverless_framework",
+    "tencent_serverless_framework",
+    "jsonnet",
+    "cluster_management",
+    "kotlin_native_linux",
+    "typo3_distribution",
+    "laravel",
+    "astro_tailwind",
+];
 
 impl<'a> Provider for GitlabArgs<'a> {
     fn payload(&self) -> String {
-        to_string(&self).unwrap()
+        // Send the generated path so the urls printed afterwards are the ones gitlab uses.
+        let mut payload = to_value(self).unwrap();
+        if self.path.is_none() {
+            payload["path"] = self.project_name().into();
+        }
+        to_string(&payload).unwrap()
     }
 
     fn endpoint(&self) -> String {
-        ENDPOINT.to_string()
+        format!("{}{}", self.api_url, ENDPOINT)
     }
 
     fn extract_url(&self, _: &reqwest::header::HeaderMap) -> String {
@@ -88,6 +167,502 @@ impl<'a> Provider for GitlabArgs<'a> {
         "Private-Token".to_string()
     }
 
+    fn name(&self) -> String {
+        self.path.or(self.name).unwrap_or_default().to_string()
+    }
+
+    fn slug(&self) -> Result<String, String> {
+        match (self.path, self.name) {
+            (Some(path), _) => slug::gitlab_path(path),
+            (None, Some(name)) => slug::gitlab_name(name),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    fn api_url(&self) -> String {
+        self.api_url.to_string()
+    }
+
+    fn repo_path(&self, api: &Api) -> Result<String, Box<dyn Error>> {
+        let namespace = match self.namespace_id {
+            Some(id) => api.get(&format!("/namespaces/{}", id))?.into_json()?["full_path"]
+                .as_str()
+                .map(str::to_string),
+            None => api.get("/user")?.into_json()?["username"]
+                .as_str()
+                .map(str::to_string),
+        }
+        .ok_or("Gitlab didn't respond with the namespace of the project.")?;
+
+        Ok(format!(
+            "/projects/{}",
+            encode_path(&format!("{}/{}", namespace, self.slug()?))
+        ))
+    }
+
+    fn preflight(&self, api: &Api) -> Result<(), Box<dyn Error>> {
+        if let Some(id) = self.template_project_id {
+            let response = api.get(&format!("/projects/{}", id))?;
+            if response.status == reqwest::StatusCode::NOT_FOUND {
+                return Err(format!(
+                    "There's no project with the id {} to use as a template.",
+                    id
+                )
+                .into());
+            }
+            response.into_json()?;
+        } else if let Some(name) = self.template_name {
+            if self.use_custom_template {
+                let path = match self.group_with_project_templates_id {
+                    Some(group) => format!(
+                        "/groups/{}/projects?include_subgroups=true&simple=true&search={}",
+                        group,
+                        encode(name)
+                    ),
+                    None => format!("/projects?simple=true&search={}", encode(name)),
+                };
+                let projects = api.get(&path)?.into_json()?;
+                let found = self
+                    .list_page(&projects)
+                    .iter()
+                    .any(|p| p["path"] == name || p["name"] == name);
+                if !found {
+                    return Err(format!("There's no custom template named `{}`.", name).into());
+                }
+            } else if !BUILT_IN_TEMPLATES.contains(&name) {
+                eprintln!(
+                    "`{}` isn't one of the built-in templates gitpub knows about, gitlab may reject it. Known templates are: {}",
+                    name,
+                    BUILT_IN_TEMPLATES.join(", ")
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn repo_urls(&self, repo: &Value) -> RepoUrls {
+        RepoUrls {
+            web: repo["web_url"].as_str().map(str::to_string),
+            https: repo["http_url_to_repo"].as_str().map(str::to_string),
+            ssh: repo["ssh_url_to_repo"].as_str().map(str::to_string),
+        }
+    }
+
+    fn list_path(&self) -> String {
+        match self.group {
+            Some(group) => format!(
+                "/groups/{}/projects?include_subgroups=true&per_page=100",
+                encode_path(group)
+            ),
+            None => "/projects?owned=true&per_page=100".to_string(),
+        }
+    }
+
+    fn next_page(&self, response: &Response, path: &str) -> Option<String> {
+        let page = response
+            .headers
+            .get("x-next-page")
+            .and_then(|x| x.to_str().ok())
+            .filter(|x| !x.is_empty())?;
+        let (base, query) = match path.find('?') {
+            Some(i) => (&path[..i], &path[i + 1..]),
+            None => (path, ""),
+        };
+        let mut params = query
+            .split('&')
+            .filter(|p| !p.is_empty() && !p.starts_with("page="))
+            .collect::<Vec<_>>();
+        let page = format!("page={}", page);
+        params.push(&page);
+        Some(format!("{}?{}", base, params.join("&")))
+    }
+
+    fn repo_summary(&self, repo: &Value) -> RepoSummary {
+        RepoSummary {
+            name: repo["path_with_namespace"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            private: repo["visibility"] != "public",
+            archived: repo["archived"].as_bool().unwrap_or_default(),
+            description: repo["description"].as_str().unwrap_or_default().to_string(),
+            url: repo["web_url"].as_str().unwrap_or_default().to_string(),
+            default_branch: repo["default_branch"].as_str().map(str::to_string),
+        }
+    }
+
+    fn full_repo_path(&self, full_name: &str) -> String {
+        format!("/projects/{}", encode_path(full_name))
+    }
+
+    fn settings(&self, repo: &Value) -> Settings {
+        let mut settings = Settings::default();
+        let name = repo["name"].as_str().unwrap_or_default();
+        let path = repo["path"].as_str();
+        // The path is only needed when it isn't the one gitlab would generate from the name.
+        if path.is_some() && slug::gitlab_name(name).ok().as_deref() == path {
+            settings.value("name", Some(name));
+        } else {
+            settings.value("path", path);
+        }
+        if repo["namespace"]["kind"] == "group" {
+            settings.value(
+                "namespace_id",
+                repo["namespace"]["id"]
+                    .as_u64()
+                    .map(|id| id.to_string())
+                    .as_deref(),
+            );
+        }
+        settings.value("visibility", repo["visibility"].as_str());
+        settings.value("description", repo["description"].as_str());
+        settings.value("default_branch", repo["default_branch"].as_str());
+        settings.flag("disable_issues", repo["issues_enabled"] == false);
+        settings.flag("disable_wiki", repo["wiki_enabled"] == false);
+        settings.flag("disable_merge", repo["merge_requests_enabled"] == false);
+        settings.flag("disable_jobs", repo["jobs_enabled"] == false);
+        settings.flag("disable_snippets", repo["snippets_enabled"] == false);
+        settings.flag(
+            "disable_container_registry",
+            repo["container_registry_enabled"] == false,
+        );
+        settings.flag(
+            "disable_shared_runners",
+            repo["shared_runners_enabled"] == false,
+        );
+        // Newer versions of the api call it `public_jobs`.
+        settings.flag(
+            "public_builds",
+            repo["public_builds"] == true || repo["public_jobs"] == true,
+        );
+        settings.flag(
+            "pipeline_success_required",
+            repo["only_allow_merge_if_pipeline_succeeds"] == true,
+        );
+        settings.flag(
+            "discussion_resolution_required",
+            repo["only_allow_merge_if_all_discussions_are_resolved"] == true,
+        );
+        settings.value("merge_method", repo["merge_method"].as_str());
+        settings.flag("lfs", repo["lfs_enabled"] == true);
+        settings.flag(
+            "request_access_enabled",
+            repo["request_access_enabled"] == true,
+        );
+        // Newer versions of the api call them `topics`.
+        let tags = repo["topics"]
+            .as_array()
+            .or_else(|| repo["tag_list"].as_array())
+            .map(|tags| {
+                tags.iter()
+                    .filter_map(|t| t.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        settings.values("tag_list", tags);
+        settings.flag(
+            "merge_request_link_on_commit",
+            repo["printing_merge_request_link_enabled"] == true,
+        );
+        settings.value("ci_config_path", repo["ci_config_path"].as_str());
+        settings.value(
+            "approvals_before_merge",
+            repo["approvals_before_merge"]
+                .as_u64()
+                .filter(|n| *n > 0)
+                .map(|n| n.to_string())
+                .as_deref(),
+        );
+        settings.flag("mirror", repo["mirror"] == true);
+        settings.flag(
+            "mirror_triggers_builds",
+            repo["mirror_trigger_builds"] == true,
+        );
+        settings
+    }
+
+    fn post_create(&self, created: &RepoSummary) -> Vec<Step<'_>> {
+        let full_name = created.name.as_str();
+        let mut steps = Vec::new();
+        // Files go in first so the protected branches don't apply to the initial commit.
+        if !self.seed.is_empty() {
+            let project = self.full_repo_path(full_name);
+            let branch = self
+                .default_branch
+                .map(str::to_string)
+                .or_else(|| created.default_branch.clone())
+                .unwrap_or_else(|| "main".to_string());
+            let initialized = self.initialize_with_readme
+                || self.template_name.is_some()
+                || self.template_project_id.is_some();
+            steps.push(Step::lookup("add the seed files", move |api| {
+                let files = self.seed.read()?;
+                let mut actions = Vec::new();
+                for file in &files {
+                    // Files from --initialize_with_readme or a template are replaced.
+                    let path = format!(
+                        "{}/repository/files/{}?ref={}",
+                        project,
+                        encode(&file.path),
+                        branch
+                    );
+                    let exists = initialized && api.get(&path)?.is_success();
+                    actions.push(json!({
+                        "action": if exists { "update" } else { "create" },
+                        "file_path": file.path,
+                        "content": base64::encode(&file.contents),
+                        "encoding": "base64",
+                        "execute_filemode": file.executable,
+                    }));
+                }
+                Ok((
+                    Method::POST,
+                    format!("{}/repository/commits", project),
+                    Some(json!({
+                        "branch": branch,
+                        "commit_message": seed::MESSAGE,
+                        "actions": actions,
+                    })),
+                ))
+            }));
+        }
+        // Gitlab protects branches by name, so they don't have to exist yet.
+        for branch in &self.protection.branches {
+            // Requiring reviews means changes have to go through a merge request.
+            let push_access_level = if self.protection.required_reviews > 0 {
+                0
+            } else {
+                40
+            };
+            steps.push(Step::new(
+                format!("protect `{}`", branch),
+                (
+                    Method::POST,
+                    format!("{}/protected_branches", self.full_repo_path(full_name)),
+                    Some(json!({
+                        "name": branch,
+                        "push_access_level": push_access_level,
+                        "merge_access_level": 40,
+                        "allow_force_push": false,
+                    })),
+                ),
+            ));
+        }
+        if self.protection.dismiss_stale_reviews {
+            steps.push(Step::new(
+                "reset approvals on push",
+                (
+                    Method::POST,
+                    format!("{}/approvals", self.full_repo_path(full_name)),
+                    Some(json!({ "reset_approvals_on_push": true })),
+                ),
+            ));
+        }
+        for member in &self.members {
+            let project = self.full_repo_path(full_name);
+            steps.push(Step::lookup(
+                format!("add `{}` as a member", member.name),
+                move |api| {
+                    let user_id = lookup_id(
+                        api,
+                        &format!("/users?username={}", encode(member.name)),
+                        format!("There's no gitlab user named `{}`.", member.name),
+                    )?;
+                    Ok((
+                        Method::POST,
+                        format!("{}/members", project),
+                        Some(json!({
+                            "user_id": user_id,
+                            "access_level": access_level(member.permission),
+                        })),
+                    ))
+                },
+            ));
+        }
+        for group in &self.groups {
+            let project = self.full_repo_path(full_name);
+            steps.push(Step::lookup(
+                format!("share the project with `{}`", group.name),
+                move |api| {
+                    let group_id = lookup_id(
+                        api,
+                        &format!("/groups/{}", encode_path(group.name)),
+                        format!("There's no gitlab group at `{}`.", group.name),
+                    )?;
+                    Ok((
+                        Method::POST,
+                        format!("{}/share", project),
+                        Some(json!({
+                            "group_id": group_id,
+                            "group_access": access_level(group.permission),
+                        })),
+                    ))
+                },
+            ));
+        }
+        for hook in &self.webhooks {
+            steps.push(Step::new(
+                format!("add the `{}` webhook", hook.url),
+                (
+                    Method::POST,
+                    format!("{}/hooks", self.full_repo_path(full_name)),
+                    Some(hook_payload(hook)),
+                ),
+            ));
+        }
+        if let Some(key) = &self.deploy_key {
+            let repo = self.full_repo_path(full_name);
+            let comment = full_name.to_string();
+            steps.push(Step::lookup(
+                format!("add the `{}` deploy key", key.title()),
+                move |_| {
+                    let public_key = key.public_key(&comment)?;
+                    Ok((
+                        Method::POST,
+                        format!("{}/deploy_keys", repo),
+                        Some(json!({ "title": key.title(), "key": public_key, "can_push": key.read_write })),
+                    ))
+                },
+            ));
+        }
+        for variable in &self.variables {
+            let project = self.full_repo_path(full_name);
+            let kind = if variable.secret {
+                "secret"
+            } else {
+                "variable"
+            };
+            steps.push(Step::lookup(
+                format!("set the `{}` {}", variable.name, kind),
+                move |_| {
+                    Ok((
+                        Method::POST,
+                        format!("{}/variables", project),
+                        Some(json!({
+                            "key": variable.name,
+                            "value": variable.value()?,
+                            "protected": self.protected_variables,
+                            // Secrets are always masked in job logs.
+                            "masked": variable.secret || self.masked_variables,
+                        })),
+                    ))
+                },
+            ));
+        }
+        let project = self.full_repo_path(full_name);
+        if self.labels.replace_defaults {
+            let project = project.clone();
+            steps.push(Step::lookup_all("remove the default labels", move |api| {
+                let existing = api
+                    .get(&format!("{}/labels?per_page=100", project))?
+                    .into_json()?;
+                Ok(existing
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter(|label| {
+                        !self
+                            .labels
+                            .contains(label["name"].as_str().unwrap_or_default())
+                    })
+                    .map(|label| {
+                        (
+                            Method::DELETE,
+                            format!("{}/labels/{}", project, label["id"]),
+                            None,
+                        )
+                    })
+                    .collect())
+            }));
+        }
+        for label in &self.labels.labels {
+            let project = project.clone();
+            steps.push(Step::lookup(
+                format!("set the `{}` label", label.name),
+                move |api| {
+                    let existing =
+                        api.get(&format!("{}/labels/{}", project, encode(&label.name)))?;
+                    let mut payload = json!({
+                        "color": format!("#{}", label.color),
+                        "description": label.description,
+                    });
+                    // Labels inherited from an instance template are updated instead.
+                    if existing.is_success() {
+                        payload["new_name"] = json!(label.name);
+                        Ok((
+                            Method::PUT,
+                            format!("{}/labels/{}", project, existing.body["id"]),
+                            Some(payload),
+                        ))
+                    } else {
+                        payload["name"] = json!(label.name);
+                        Ok((Method::POST, format!("{}/labels", project), Some(payload)))
+                    }
+                },
+            ));
+        }
+        steps.extend(self.boilerplate.local_step());
+        steps
+    }
+
+    fn archive_request(&self, full_name: &str, archived: bool) -> Result<Request, Box<dyn Error>> {
+        let action = if archived { "archive" } else { "unarchive" };
+        Ok((
+            Method::POST,
+            format!("{}/{}", self.full_repo_path(full_name), action),
+            None,
+        ))
+    }
+
+    fn rename_request(&self, full_name: &str, new_name: &str) -> Request {
+        // The path has to change too, otherwise the urls keep the old name.
+        let path = slug::gitlab_name(new_name).unwrap_or_else(|_| new_name.to_string());
+        (
+            Method::PUT,
+            self.full_repo_path(full_name),
+            Some(json!({ "name": new_name, "path": path })),
+        )
+    }
+
+    fn transfer_request(
+        &self,
+        full_name: &str,
+        new_owner: &str,
+    ) -> Result<Request, Box<dyn Error>> {
+        Ok((
+            Method::PUT,
+            format!("{}/transfer", self.full_repo_path(full_name)),
+            Some(json!({ "namespace": new_owner })),
+        ))
+    }
+
+    fn fork_path(&self, full_name: &str) -> String {
+        format!("{}/fork", self.full_repo_path(full_name))
+    }
+
+    fn fork_payload(&self) -> Value {
+        match self.namespace_id {
+            Some(id) => json!({ "namespace_id": id }),
+            None => json!({}),
+        }
+    }
+
+    fn fork_ready(&self, api: &Api, fork: &Value) -> Result<bool, Box<dyn Error>> {
+        let project = api.get(&format!("/projects/{}", fork["id"]))?.into_json()?;
+        match project["import_status"].as_str() {
+            Some("failed") => Err(format!(
+                "Gitlab couldn't copy the fork: {}",
+                project["import_error"]
+                    .as_str()
+                    .unwrap_or("no reason was given")
+            )
+            .into()),
+            Some("none") | Some("finished") | None => Ok(true),
+            Some(_) => Ok(false),
+        }
+    }
+
     fn ssh_url(&self, _: &reqwest::header::HeaderMap) -> Option<String> {
         match std::env::var("GITLAB_USERNAME") {
             Ok(u) => Some(format!("git@gitlab.com:{}/{}.git", u, self.project_name())),
@@ -96,36 +671,170 @@ impl<'a> Provider for GitlabArgs<'a> {
     }
 }
 
+/// Gitlab's access level for a permission.
+fn access_level(permission: Permission) -> u32 {
+    match permission {
+        Permission::Read | Permission::Triage => 20,
+        Permission::Write => 30,
+        Permission::Maintain => 40,
+        Permission::Admin => 50,
+    }
+}
+
+/// The hook payload for a webhook, turning github's event names into gitlab's `*_events` flags.
+fn hook_payload(hook: &Webhook) -> Value {
+    // Gitlab sends push events unless told otherwise.
+    let mut payload = json!({ "url": hook.url, "push_events": false });
+    for event in &hook.events {
+        let event = match *event {
+            "pull_request" => "merge_requests",
+            "issue_comment" => "note",
+            "create" => "tag_push",
+            "release" => "releases",
+            "gollum" => "wiki_page",
+            other => other,
+        };
+        payload[format!("{}_events", event)] = json!(true);
+    }
+    if let Some(secret) = &hook.secret {
+        payload["token"] = json!(secret);
+    }
+    payload
+}
+
+/// The id of the first result of a lookup, like `/users?username=tanuki`, or the object itself, like `/groups/team`.
+fn lookup_id(api: &Api, path: &str, missing: String) -> Result<u64, Box<dyn Error>> {
+    let found = api.get(path)?.into_json()?;
+    let found = match found.as_array() {
+        Some(results) => results.first().cloned().unwrap_or(Value::Null),
+        None => found,
+    };
+    found["id"].as_u64().ok_or_else(|| missing.into())
+}
+
+/// Project paths are passed to the api as a single url-encoded segment.
+fn encode_path(path: &str) -> String {
+    path.replace('/', "%2F")
+}
+
+fn validate_id(id: String) -> Result<(), String> {
+    id.parse::<u32>()
+        .map(|_| ())
+        .map_err(|_| format!("`{}` isn't an id, like 42.", id))
+}
+
+fn name_arg() -> Arg<'static, 'static> {
+    Arg::with_name("name")
+        .short("n")
+        .long("name")
+        .help("The name of the new repository. Defaults to the name of the git toplevel directory.")
+        .conflicts_with("path")
+        .takes_value(true)
+}
+
+fn path_arg() -> Arg<'static, 'static> {
+    Arg::with_name("path")
+        .long("path")
+        .help("The path for the new repo. Generated based on name if not provided (generated lowercased with dashes).")
+        .conflicts_with("name")
+        .takes_value(true)
+}
+
+fn token_arg() -> Arg<'static, 'static> {
+    Arg::with_name("token")
+        .short("t")
+        .long("token")
+        .help("A personal access token. Alternatively read from GITLAB_REPO_TOKEN env variable.")
+        .env("GITLAB_REPO_TOKEN")
+        .takes_value(true)
+        .required(true)
+}
+
+fn api_url_arg() -> Arg<'static, 'static> {
+    Arg::with_name("api_url")
+        .long("api_url")
+        .help("The root of the gitlab api, useful for self-hosted instances. Alternatively read from GITLAB_API_URL env variable.")
+        .env("GITLAB_API_URL")
+        .takes_value(true)
+        .default_value(API_URL)
+}
+
+fn namespace_arg() -> Arg<'static, 'static> {
+    Arg::with_name("namespace_id")
+        .long("namespace_id")
+        .help("Namespace for the new project (defaults to the current user’s namespace)")
+        .takes_value(true)
+}
+
 pub fn subcommand() -> App<'static, 'static> {
     SubCommand::with_name("gitlab")
         .version(env!("CARGO_PKG_VERSION"))
         .about("Create a repo on gitlab.")
-        .arg(
-            Arg::with_name("name")
-                .short("n")
-                .long("name")
-                .help("The name of the new repository.")
-                .conflicts_with("path")
-                .required_unless("path")
-                .takes_value(true)
-        ).arg(
-            Arg::with_name("path")
-                .long("path")
-                .help("The path for the new repo. Generated based on name if not provided (generated lowercased with dashes).")
-                .conflicts_with("name")
-                .required_unless("name")
-                .takes_value(true)
-        ).arg(
-            Arg::with_name("token")
-                .short("t")
-                .long("token")
-                .help(
-                    "A personal access token. Alternatively read from GITLAB_REPO_TOKEN env variable.",
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Checks whether a repo with this name already exists.")
+                .arg(name_arg())
+                .arg(path_arg())
+                .arg(token_arg())
+                .arg(api_url_arg())
+                .arg(namespace_arg())
+        )
+        .subcommand(
+            action::list_subcommand()
+                .arg(token_arg())
+                .arg(api_url_arg())
+                .arg(
+                    Arg::with_name("group")
+                        .long("group")
+                        .takes_value(true)
+                        .help("Lists the projects of a group, by id or path, instead of your own.")
                 )
-                .env("GITLAB_REPO_TOKEN")
-                .takes_value(true)
-                .required(true)
-        ).arg(
+        )
+        .subcommand(
+            action::show_subcommand()
+                .arg(token_arg())
+                .arg(api_url_arg())
+        )
+        .subcommand(action::archive_subcommand("archive").arg(token_arg()).arg(api_url_arg()))
+        .subcommand(action::archive_subcommand("unarchive").arg(token_arg()).arg(api_url_arg()))
+        .subcommand(
+            action::rename_subcommand()
+                .arg(token_arg())
+                .arg(api_url_arg())
+        )
+        .subcommand(
+            action::transfer_subcommand()
+                .arg(token_arg())
+                .arg(api_url_arg())
+        )
+        .subcommand(
+            action::fork_subcommand()
+                .arg(token_arg())
+                .arg(api_url_arg())
+                .arg(namespace_arg().help("The id of the namespace to fork into. Defaults to your own."))
+        )
+        .arg(name_arg())
+        .arg(path_arg())
+        .arg(token_arg())
+        .arg(api_url_arg())
+        .arg(action::like_arg())
+        .args(&action::unlike_args(&[
+            "disable_issues",
+            "disable_wiki",
+            "disable_merge",
+            "disable_jobs",
+            "disable_snippets",
+            "disable_container_registry",
+            "disable_shared_runners",
+            "public_builds",
+            "pipeline_success_required",
+            "discussion_resolution_required",
+            "lfs",
+            "request_access_enabled",
+            "merge_request_link_on_commit",
+        ]))
+        .arg(
             Arg::with_name("visibility")
                 .long("visibility")
                 .takes_value(true)
@@ -147,12 +856,8 @@ pub fn subcommand() -> App<'static, 'static> {
                 .short("w")
                 .long("disable_wiki")
                 .help("Disables wikis for this repo.")
-        ).arg(
-            Arg::with_name("namespace_id")
-                .long("namespace_id")
-                .help("Namespace for the new project (defaults to the current user’s namespace)")
-                .takes_value(true)
-        ).arg(
+        ).arg(namespace_arg())
+        .arg(
             Arg::with_name("disable_merge")
                 .long("disable_merge")
                 .help("Disables merging with a merge commit.")
@@ -213,10 +918,10 @@ pub fn subcommand() -> App<'static, 'static> {
         ).arg(
             Arg::with_name("tag_list")
                 .long("tag_list")
+                .alias("topic")
                 .takes_value(true)
                 .multiple(true)
-                .max_values(4)
-                .help("A list of tags for a repo. Takes up to four params. `--tag_list first second third tag`")
+                .help("Topics for the repo, also accepted as --topic. `--tag_list first second third tag`")
         )
         .arg(
             Arg::with_name("merge_request_link_on_commit")
@@ -250,48 +955,151 @@ pub fn subcommand() -> App<'static, 'static> {
                 .short("r")
                 .long("initialize_with_readme")
                 .help("Create a blank README for the project.")
+        ).arg(
+            Arg::with_name("template_name")
+                .long("template_name")
+                .takes_value(true)
+                .help("Starts the project from a built-in template, ex 'rails' or 'spring', or a custom template by name with --use_custom_template.")
+                .conflicts_with_all(&["import_url", "initialize_with_readme"])
+        ).arg(
+            Arg::with_name("template_project_id")
+                .long("template_project_id")
+                .takes_value(true)
+                .validator(validate_id)
+                .help("Starts the project from a custom template by project id. Preferred over --template_name for custom templates.")
+                .conflicts_with_all(&["import_url", "initialize_with_readme"])
+        ).arg(
+            Arg::with_name("use_custom_template")
+                .long("use_custom_template")
+                .help("Looks up --template_name in the instance or group's custom templates instead of the built-in ones.")
+        ).arg(
+            Arg::with_name("group_with_project_templates_id")
+                .long("group_with_project_templates_id")
+                .takes_value(true)
+                .validator(validate_id)
+                .help("The id of the group whose custom templates are used. Implies --use_custom_template.")
+        ).args(&protect::args())
+        .arg(protect::linear_history_arg().help("Prevents merge commits on protected branches by switching to fast-forward merges."))
+        .args(&deploy_key::args())
+        .arg(deploy_key::read_write_arg())
+        .args(&seed::args())
+        .args(&boilerplate::args())
+        .args(&label::args())
+        .arg(variable::variable_arg().help("Adds a CI/CD variable after creation, ex `--variable REGISTRY=registry.gitlab.com`."))
+        .arg(variable::secret_arg().help("Adds a masked CI/CD variable after creation, read from a file with `--secret NAME=@file` or from an environment variable with `--secret NAME=env:VAR`."))
+        .arg(
+            Arg::with_name("protected_variables")
+                .long("protected_variables")
+                .help("Only exposes the --variable and --secret values to pipelines on protected branches and tags.")
+        ).arg(
+            Arg::with_name("masked_variables")
+                .long("masked_variables")
+                .help("Masks the --variable values in job logs too. Secrets are always masked.")
         )
+        .arg(webhook::arg().help("Registers a webhook after creation, ex `--webhook https://ci.example.com/hook,events=push,pull_request,secret_env=CI_SECRET`. Events use github's names, like pull_request for merge requests, or gitlab's without `_events`, like pipeline. They default to push. The secret is read from the named environment variable and sent as the hook's token."))
+        .arg(access::collaborator_arg().help("Adds a user as a member after creation, ex `--collaborator tanuki:maintainer`. Permissions are read, write, maintain or admin, or gitlab's reporter, developer, maintainer or owner. Defaults to write."))
+        .arg(access::team_arg().help("Shares the project with a group by its path after creation, ex `--team platform/backend:developer`. Takes the same permissions as --collaborator."))
 }
 
-pub fn from_matches<'a>(matches: &'a ArgMatches) -> GitlabArgs<'a> {
+pub fn from_matches<'a>(
+    matches: &'a ArgMatches<'a>,
+    project: &'a Project,
+    like: &'a Settings,
+) -> GitlabArgs<'a> {
+    let settings = LikeMatches::new(matches, like);
+    let protection = Protection::from_matches(matches);
+    let path = matches.value_of("path");
+    let id = |name| {
+        matches.value_of(name).map(|x| {
+            x.parse::<u32>()
+                .expect("This is validated by clap. This error should be unreachable.")
+        })
+    };
+    let template_project_id = id("template_project_id");
+    let group_with_project_templates_id = id("group_with_project_templates_id");
+    let boilerplate = Boilerplate::from_matches(matches);
+    let mut seed = Seed::from_matches(matches);
+    boilerplate.add_to(&mut seed);
     GitlabArgs {
-        name: matches.value_of("name"),
+        name: match path {
+            Some(_) => None,
+            None => Some(project::name(matches, project)),
+        },
         token: matches
             .value_of("token")
             .expect("Couldn't get the token. This should be unreachable."),
-        path: matches.value_of("path"),
-        visibility: matches.value_of("visibility"),
-        description: matches.value_of("description"),
-        issues_enabled: !matches.is_present("disable_issues"),
-        wiki_enabled: !matches.is_present("disable_wiki"),
+        api_url: matches.value_of("api_url").unwrap_or(API_URL),
+        group: matches.value_of("group"),
+        path,
+        visibility: settings.value_of("visibility"),
+        description: matches
+            .value_of("description")
+            .or(project.description.as_deref())
+            .or_else(|| like.value_of("description")),
+        issues_enabled: !settings.is_present("disable_issues"),
+        wiki_enabled: !settings.is_present("disable_wiki"),
         namespace_id: matches
             .value_of("namespace_id")
             .and_then(|x| x.parse::<u32>().ok()),
-        merge_requests_enabled: matches.is_present("disable_merge"),
-        default_branch: matches.value_of("default_branch"),
-        jobs_enabled: !matches.is_present("disable_jobs"),
-        snippets_enabled: !matches.is_present("disable_snippets"),
-        container_registry_enabled: !matches.is_present("disable_container_registry"),
-        shared_runners_enabled: !matches.is_present("disable_shared_runners"),
+        merge_requests_enabled: !settings.is_present("disable_merge"),
+        default_branch: settings.value_of("default_branch"),
+        jobs_enabled: !settings.is_present("disable_jobs"),
+        snippets_enabled: !settings.is_present("disable_snippets"),
+        container_registry_enabled: !settings.is_present("disable_container_registry"),
+        shared_runners_enabled: !settings.is_present("disable_shared_runners"),
         import_url: matches.value_of("import_url"),
-        public_builds: matches.is_present("public_builds"),
-        only_allow_merge_if_pipeline_succeeds: matches.is_present("pipeline_success_required"),
-        only_allow_merge_if_all_discussions_are_resolved: matches
+        public_builds: settings.is_present("public_builds"),
+        only_allow_merge_if_pipeline_succeeds: settings.is_present("pipeline_success_required")
+            || !protection.status_checks.is_empty(),
+        only_allow_merge_if_all_discussions_are_resolved: settings
             .is_present("discussion_resolution_required"),
-        merge_method: matches.value_of("merge_method"),
-        lfs_enabled: matches.is_present("lfs"),
-        request_access_enabled: matches.is_present("request_access_enabled"),
+        merge_method: settings
+            .value_of("merge_method")
+            .or(Some("ff").filter(|_| protection.linear_history)),
+        lfs_enabled: settings.is_present("lfs"),
+        request_access_enabled: settings.is_present("request_access_enabled"),
         tag_list: matches
             .values_of("tag_list")
-            .and_then(|x| Some(x.collect::<Vec<_>>())),
-        printing_merge_request_link_enabled: matches.is_present("merge_request_link_on_commit"),
-        ci_config_path: matches.value_of("ci_config_path"),
+            .map(|x| x.collect::<Vec<_>>())
+            .or_else(|| {
+                Some(
+                    project
+                        .keywords
+                        .iter()
+                        .map(String::as_str)
+                        .collect::<Vec<_>>(),
+                )
+                .filter(|k| !k.is_empty())
+            })
+            .or_else(|| like.values_of("tag_list")),
+        printing_merge_request_link_enabled: settings.is_present("merge_request_link_on_commit"),
+        ci_config_path: settings.value_of("ci_config_path"),
         repository_storage: matches.value_of("repository_storage"),
-        approvals_before_merge: matches
+        approvals_before_merge: settings
             .value_of("approvals_before_merge")
-            .and_then(|x| x.parse::<u32>().ok()),
+            .and_then(|x| x.parse::<u32>().ok())
+            .or(Some(protection.required_reviews).filter(|n| *n > 0)),
+        // Mirroring needs an import url, which is specific to the repo being created.
         mirror: matches.is_present("mirror"),
-        mirror_trigger_builds: matches.is_present("mirror_trigger_builds"),
+        mirror_trigger_builds: matches.is_present("mirror_triggers_builds"),
         initialize_with_readme: matches.is_present("initialize_with_readme"),
+        template_name: matches.value_of("template_name"),
+        template_project_id,
+        // Custom templates are the only kind with project or group ids.
+        use_custom_template: matches.is_present("use_custom_template")
+            || template_project_id.is_some()
+            || group_with_project_templates_id.is_some(),
+        group_with_project_templates_id,
+        protection,
+        members: Grant::from_matches(matches, "collaborator"),
+        groups: Grant::from_matches(matches, "team"),
+        webhooks: Webhook::from_matches(matches),
+        deploy_key: DeployKey::from_matches(matches),
+        variables: Variable::from_matches(matches),
+        labels: Labels::from_matches(matches),
+        seed,
+        boilerplate,
+        protected_variables: matches.is_present("protected_variables"),
+        masked_variables: matches.is_present("masked_variables"),
     }
 }
@@ -0,0 +1,89 @@
+//! Issue labels created once a repo is created, read from a toml file.
+use clap::{Arg, ArgMatches};
+use serde::Deserialize;
+use std::fs;
+
+/// A label from a file like:
+///
+/// ```toml
+/// [[label]]
+/// name = "bug"
+/// color = "d73a4a"
+/// description = "Something isn't working"
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Label {
+    pub name: String,
+    /// Six hex digits, without the `#`.
+    pub color: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Deserialize)]
+struct LabelFile {
+    #[serde(default)]
+    label: Vec<Label>,
+}
+
+/// The labels to set and whether to remove the ones the provider created.
+#[derive(Default)]
+pub struct Labels {
+    pub labels: Vec<Label>,
+    pub replace_defaults: bool,
+}
+
+impl Labels {
+    pub fn from_matches(matches: &ArgMatches) -> Labels {
+        Labels {
+            labels: matches
+                .value_of("labels")
+                .map(|path| {
+                    load(path).expect("Labels are validated by clap. This should be unreachable.")
+                })
+                .unwrap_or_default(),
+            replace_defaults: matches.is_present("replace_default_labels"),
+        }
+    }
+
+    /// Whether a label named `name` is in the file. Label names are case insensitive.
+    pub fn contains(&self, name: &str) -> bool {
+        self.labels
+            .iter()
+            .any(|label| label.name.eq_ignore_ascii_case(name))
+    }
+}
+
+fn load(path: &str) -> Result<Vec<Label>, String> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| format!("Couldn't read `{}`: {}", path, e))?;
+    let mut labels = toml::from_str::<LabelFile>(&contents)
+        .map_err(|e| format!("`{}` isn't a valid labels file: {}", path, e))?
+        .label;
+    for label in &mut labels {
+        label.color = label.color.trim_start_matches('#').to_lowercase();
+        if label.color.len() != 6 || !label.color.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!(
+                "The color of `{}` should be six hex digits, like `d73a4a`.",
+                label.name
+            ));
+        }
+    }
+    Ok(labels)
+}
+
+pub fn args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("labels")
+            .long("labels")
+            .takes_value(true)
+            .value_name("labels.toml")
+            .validator(|path| load(&path).map(|_| ()))
+            .help("Creates or updates the issue labels in a toml file after creation. Each `[[label]]` has a name, a color like `d73a4a` and an optional description."),
+        Arg::with_name("replace_default_labels")
+            .long("replace_default_labels")
+            .requires("labels")
+            .help("Removes the labels the provider created that aren't in the --labels file."),
+    ]
+}
@@ -1,6 +1,336 @@
+use crate::api::{Api, Response};
+use clap::ArgMatches;
+use reqwest::Method;
+use serde::Serialize;
+use serde_json::Value;
+use std::error::Error;
+
+pub mod access;
 pub mod bitbucket;
+pub mod boilerplate;
+pub mod deploy_key;
 pub mod github;
 pub mod gitlab;
+pub mod label;
+pub mod protect;
+pub mod seed;
+pub mod slug;
+pub mod variable;
+pub mod webhook;
+
+/// Percent-encodes a path segment, like a label name or a file path with its slashes.
+pub fn encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// A request to the provider's api as its method, path relative to the api root and JSON body.
+pub type Request = (Method, String, Option<Value>);
+
+/// A request made once the repo is created, for options the create request can't carry.
+pub struct Step<'a> {
+    /// What the step does, for reporting, like `set the topics`.
+    pub description: String,
+    run: Box<Run<'a>>,
+}
+
+type Run<'a> = dyn Fn(&Api) -> Result<(), Box<dyn Error>> + 'a;
+
+impl<'a> Step<'a> {
+    /// A step whose request is known up front.
+    pub fn new(description: impl Into<String>, request: Request) -> Step<'a> {
+        Step::lookup(description, move |_| Ok(request.clone()))
+    }
+
+    /// A step whose request needs something from the api first.
+    pub fn lookup(
+        description: impl Into<String>,
+        request: impl Fn(&Api) -> Result<Request, Box<dyn Error>> + 'a,
+    ) -> Step<'a> {
+        Step::lookup_all(description, move |api| Ok(vec![request(api)?]))
+    }
+
+    /// A step whose requests depend on what the api returns, like deleting every existing label.
+    pub fn lookup_all(
+        description: impl Into<String>,
+        requests: impl Fn(&Api) -> Result<Vec<Request>, Box<dyn Error>> + 'a,
+    ) -> Step<'a> {
+        Step::with_api(description, move |api| {
+            for (method, path, body) in requests(api)? {
+                api.request(method, &path, body.as_ref())?.into_json()?;
+            }
+            Ok(())
+        })
+    }
+
+    /// A step that only changes the working tree, like writing the license.
+    pub fn local(
+        description: impl Into<String>,
+        run: impl Fn() -> Result<(), Box<dyn Error>> + 'a,
+    ) -> Step<'a> {
+        Step::with_api(description, move |_| run())
+    }
+
+    /// A step that talks to the api itself, for requests that each need the previous response
+    /// or that aren't JSON, like uploading files.
+    pub fn with_api(
+        description: impl Into<String>,
+        run: impl Fn(&Api) -> Result<(), Box<dyn Error>> + 'a,
+    ) -> Step<'a> {
+        Step {
+            description: description.into(),
+            run: Box::new(run),
+        }
+    }
+
+    pub fn run(&self, api: &Api) -> Result<(), Box<dyn Error>> {
+        (self.run)(api)
+    }
+}
+
+/// Urls of an existing repository, as reported by the provider's api.
+#[derive(Default, Debug)]
+pub struct RepoUrls {
+    pub web: Option<String>,
+    pub https: Option<String>,
+    pub ssh: Option<String>,
+}
+
+/// The details gitpub shows when listing repositories.
+#[derive(Serialize, Debug)]
+pub struct RepoSummary {
+    pub name: String,
+    pub private: bool,
+    pub archived: bool,
+    pub description: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_branch: Option<String>,
+}
+
+/// An existing repo's settings as the creation args that would reproduce them, like `disable_wiki` or `homepage`.
+///
+/// Flags are only recorded when they're set, so a repo using every default has no settings.
+#[derive(Default, Debug)]
+pub struct Settings(Vec<(&'static str, Setting)>);
+
+#[derive(Debug)]
+pub enum Setting {
+    Flag,
+    Value(String),
+    Values(Vec<String>),
+}
+
+impl Settings {
+    /// Records a flag, like `private`, if it's set.
+    pub fn flag(&mut self, name: &'static str, set: bool) {
+        if set {
+            self.0.push((name, Setting::Flag));
+        }
+    }
+
+    /// Records an option's value unless it's missing or empty.
+    pub fn value(&mut self, name: &'static str, value: Option<&str>) {
+        if let Some(value) = value.filter(|v| !v.is_empty()) {
+            self.0.push((name, Setting::Value(value.to_string())));
+        }
+    }
+
+    /// Records an option taking several values unless there are none.
+    pub fn values(&mut self, name: &'static str, values: Vec<String>) {
+        if !values.is_empty() {
+            self.0.push((name, Setting::Values(values)));
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Setting> {
+        self.0.iter().find(|(n, _)| *n == name).map(|(_, s)| s)
+    }
+
+    pub fn is_present(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn value_of(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            Setting::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn values_of(&self, name: &str) -> Option<Vec<&str>> {
+        match self.get(name)? {
+            Setting::Values(values) => Some(values.iter().map(String::as_str).collect()),
+            _ => None,
+        }
+    }
+
+    /// The settings as command line args, like `["--private", "--homepage", "https://example.com"]`.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for (name, setting) in &self.0 {
+            args.push(format!("--{}", name));
+            match setting {
+                Setting::Flag => {}
+                Setting::Value(value) => args.push(value.clone()),
+                Setting::Values(values) => args.extend(values.iter().cloned()),
+            }
+        }
+        args
+    }
+
+    /// The settings as a JSON object keyed by arg name, where flags are `true`.
+    pub fn to_json(&self) -> Value {
+        let map = self
+            .0
+            .iter()
+            .map(|(name, setting)| {
+                let value = match setting {
+                    Setting::Flag => Value::Bool(true),
+                    Setting::Value(value) => Value::from(value.as_str()),
+                    Setting::Values(values) => Value::from(values.clone()),
+                };
+                (name.to_string(), value)
+            })
+            .collect();
+        Value::Object(map)
+    }
+}
+
+/// The flags copied from `--like` that can be turned back off, with the flag that does it and its help.
+pub const NEGATIONS: &[(&str, &str, &str)] = &[
+    (
+        "private",
+        "public",
+        "Makes the repo public even though the --like repo is private.",
+    ),
+    (
+        "disable_issues",
+        "enable_issues",
+        "Turns off --disable_issues copied from --like.",
+    ),
+    (
+        "disable_projects",
+        "enable_projects",
+        "Turns off --disable_projects copied from --like.",
+    ),
+    (
+        "disable_wiki",
+        "enable_wiki",
+        "Turns off --disable_wiki copied from --like.",
+    ),
+    (
+        "disable_squash",
+        "enable_squash",
+        "Turns off --disable_squash copied from --like.",
+    ),
+    (
+        "disable_merge",
+        "enable_merge",
+        "Turns off --disable_merge copied from --like.",
+    ),
+    (
+        "disable_rebase",
+        "enable_rebase",
+        "Turns off --disable_rebase copied from --like.",
+    ),
+    (
+        "is_template",
+        "not_template",
+        "Turns off --is_template copied from --like.",
+    ),
+    (
+        "disable_jobs",
+        "enable_jobs",
+        "Turns off --disable_jobs copied from --like.",
+    ),
+    (
+        "disable_snippets",
+        "enable_snippets",
+        "Turns off --disable_snippets copied from --like.",
+    ),
+    (
+        "disable_container_registry",
+        "enable_container_registry",
+        "Turns off --disable_container_registry copied from --like.",
+    ),
+    (
+        "disable_shared_runners",
+        "enable_shared_runners",
+        "Turns off --disable_shared_runners copied from --like.",
+    ),
+    (
+        "public_builds",
+        "private_builds",
+        "Turns off --public_builds copied from --like.",
+    ),
+    (
+        "pipeline_success_required",
+        "pipeline_success_optional",
+        "Turns off --pipeline_success_required copied from --like.",
+    ),
+    (
+        "discussion_resolution_required",
+        "discussion_resolution_optional",
+        "Turns off --discussion_resolution_required copied from --like.",
+    ),
+    (
+        "lfs",
+        "no_large_file_support",
+        "Turns off --large_file_support copied from --like.",
+    ),
+    (
+        "request_access_enabled",
+        "request_access_disabled",
+        "Turns off --request_access_enabled copied from --like.",
+    ),
+    (
+        "merge_request_link_on_commit",
+        "no_merge_request_link_on_commit",
+        "Turns off --merge_request_link_on_commit copied from --like.",
+    ),
+];
+
+/// The flag that turns off `flag` when it's copied from `--like`, like `public` for `private`.
+fn negation(flag: &str) -> Option<&'static str> {
+    NEGATIONS
+        .iter()
+        .find(|(name, _, _)| *name == flag)
+        .map(|(_, negation, _)| *negation)
+}
+
+/// Creation args from the command line, falling back to the settings of the repo passed to `--like`.
+///
+/// Values on the command line replace the repo's, and flags the repo has set can be turned off with
+/// their negation from `NEGATIONS`, like `--public` for `--private`.
+pub struct LikeMatches<'a> {
+    matches: &'a ArgMatches<'a>,
+    like: &'a Settings,
+}
+
+impl<'a> LikeMatches<'a> {
+    pub fn new(matches: &'a ArgMatches<'a>, like: &'a Settings) -> LikeMatches<'a> {
+        LikeMatches { matches, like }
+    }
+
+    pub fn is_present(&self, name: &str) -> bool {
+        let negated = negation(name).is_some_and(|negation| self.matches.is_present(negation));
+        self.matches.is_present(name) || (self.like.is_present(name) && !negated)
+    }
+
+    pub fn value_of(&self, name: &str) -> Option<&'a str> {
+        self.matches
+            .value_of(name)
+            .or_else(|| self.like.value_of(name))
+    }
+}
 
 /// Provider outlines the requirements for a provider
 pub trait Provider {
@@ -14,6 +344,63 @@ pub trait Provider {
     fn token(&self) -> String;
     /// The response header key. Like `Authorization` or `Bearer`
     fn auth_header(&self) -> String;
+    /// The name requested for the repository.
+    fn name(&self) -> String;
+    /// The name as it will appear in urls, or why the provider would reject it.
+    fn slug(&self) -> Result<String, String>;
+    /// The root of the provider's api, like `https://api.github.com`.
+    fn api_url(&self) -> String;
+    /// The api path of the repository relative to `api_url`, like `/repos/{owner}/{name}`.
+    ///
+    /// Finding the owner can take a request, for example to look up the authenticated user.
+    fn repo_path(&self, api: &Api) -> Result<String, Box<dyn Error>>;
+    /// Checks options that can't be used together, which clap can't tell on its own.
+    fn check_options(&self) -> Result<(), String> {
+        Ok(())
+    }
+    /// Checks options the provider would reject that can only be checked through the api, like a missing template.
+    fn preflight(&self, _api: &Api) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+    /// Extracts the urls of a repository from the provider's api response.
+    fn repo_urls(&self, repo: &Value) -> RepoUrls;
+    /// The api path listing the repositories of the owner passed to `list`, or the authenticated user's.
+    fn list_path(&self) -> String;
+    /// The repositories in a page of a listing.
+    fn list_page(&self, page: &Value) -> Vec<Value> {
+        page.as_array().cloned().unwrap_or_default()
+    }
+    /// The url of the page after this one in a listing, if there is one.
+    fn next_page(&self, response: &Response, _path: &str) -> Option<String> {
+        response.next_link()
+    }
+    /// Summarizes a repository from the provider's api response.
+    fn repo_summary(&self, repo: &Value) -> RepoSummary;
+    /// The api path of a repository given as `owner/name`, like `octocat/hello-world`.
+    fn full_repo_path(&self, full_name: &str) -> String;
+    /// The creation args that would reproduce a repository from the provider's api response.
+    fn settings(&self, repo: &Value) -> Settings;
+    /// The api path to POST to for forking a repository given as `owner/name`.
+    fn fork_path(&self, full_name: &str) -> String {
+        format!("{}/forks", self.full_repo_path(full_name))
+    }
+    /// The body of the fork request, naming where the fork goes if it isn't the authenticated user.
+    fn fork_payload(&self) -> Value;
+    /// The steps that finish setting up a repository just created, named `owner/name` in its summary.
+    fn post_create(&self, _created: &RepoSummary) -> Vec<Step<'_>> {
+        Vec::new()
+    }
+    /// The request that archives or unarchives a repository given as `owner/name`.
+    fn archive_request(&self, full_name: &str, archived: bool) -> Result<Request, Box<dyn Error>>;
+    /// The request that renames a repository given as `owner/name`, keeping its owner.
+    fn rename_request(&self, full_name: &str, new_name: &str) -> Request;
+    /// The request that moves a repository given as `owner/name` to another user, org, group or workspace.
+    fn transfer_request(&self, full_name: &str, new_owner: &str)
+        -> Result<Request, Box<dyn Error>>;
+    /// Whether a fork from the provider's api response has finished copying the original.
+    fn fork_ready(&self, _api: &Api, _fork: &Value) -> Result<bool, Box<dyn Error>> {
+        Ok(true)
+    }
     /// Function that extracts ssh url format from headers. If this fails origin is unset (assuming the param was passed).
     fn ssh_url(&self, _: &reqwest::header::HeaderMap) -> Option<String> {
         None
@@ -0,0 +1,91 @@
+//! The branch protection rules passed with `--protect` and the options that tune it, read into one
+//! struct that github turns into a ruleset and gitlab and bitbucket into their own branch settings.
+use clap::{Arg, ArgMatches};
+
+/// The branches to protect and the rules to protect them with.
+#[derive(Default)]
+pub struct Protection<'a> {
+    pub branches: Vec<&'a str>,
+    pub required_reviews: u32,
+    pub status_checks: Vec<&'a str>,
+    pub dismiss_stale_reviews: bool,
+    pub linear_history: bool,
+    pub enforce_admins: bool,
+}
+
+impl<'a> Protection<'a> {
+    pub fn from_matches(matches: &'a ArgMatches<'a>) -> Protection<'a> {
+        Protection {
+            branches: matches
+                .values_of("protect")
+                .map(|x| x.collect())
+                .unwrap_or_default(),
+            required_reviews: matches
+                .value_of("required_reviews")
+                .map(|x| {
+                    x.parse::<u32>()
+                        .expect("Reviews are validated by clap. This should be unreachable.")
+                })
+                .unwrap_or_default(),
+            status_checks: matches
+                .values_of("required_status_checks")
+                .map(|x| x.collect())
+                .unwrap_or_default(),
+            dismiss_stale_reviews: matches.is_present("dismiss_stale_reviews"),
+            linear_history: matches.is_present("linear_history"),
+            enforce_admins: matches.is_present("enforce_admins"),
+        }
+    }
+}
+
+fn validate_reviews(reviews: String) -> Result<(), String> {
+    reviews
+        .parse::<u32>()
+        .map(|_| ())
+        .map_err(|_| format!("`{}` isn't a number of reviews, like 2.", reviews))
+}
+
+/// The options every provider can protect a branch with. Github and gitlab also take
+/// `linear_history_arg`, and github `enforce_admins_arg`.
+pub fn args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("protect")
+            .long("protect")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("branch")
+            .help("Protects a branch right after creation, ex `--protect main`. Works before the branch has been pushed."),
+        Arg::with_name("required_reviews")
+            .long("required_reviews")
+            .takes_value(true)
+            .requires("protect")
+            .validator(validate_reviews)
+            .help("Requires this many approving reviews before merging into a protected branch."),
+        Arg::with_name("required_status_checks")
+            .long("required_status_checks")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .requires("protect")
+            .help("Requires a status check to pass before merging into a protected branch. Gitlab and bitbucket require the pipeline or builds to pass instead of named checks."),
+        Arg::with_name("dismiss_stale_reviews")
+            .long("dismiss_stale_reviews")
+            .requires("protect")
+            .help("Dismisses approvals when new commits are pushed to a pull request."),
+    ]
+}
+
+pub fn linear_history_arg() -> Arg<'static, 'static> {
+    Arg::with_name("linear_history")
+        .long("linear_history")
+        .requires("protect")
+        .help("Prevents merge commits on protected branches.")
+}
+
+pub fn enforce_admins_arg() -> Arg<'static, 'static> {
+    Arg::with_name("enforce_admins")
+        .long("enforce_admins")
+        .requires("protect")
+        .help("Applies the protection to admins too.")
+}
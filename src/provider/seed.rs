@@ -0,0 +1,157 @@
+//! Files committed to a repo once it's created, without a local checkout.
+use clap::{Arg, ArgMatches};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+pub const MESSAGE: &str = "Add initial files";
+
+/// A file to commit, read from disk.
+pub struct SeedFile {
+    /// The path in the repo, separated by `/`.
+    pub path: String,
+    pub contents: Vec<u8>,
+    pub executable: bool,
+}
+
+/// The files passed with `--file path/in/repo=local/path` and the contents of a `--skeleton` directory,
+/// along with files gitpub generates like the license.
+#[derive(Default)]
+pub struct Seed<'a> {
+    files: Vec<(&'a str, &'a str)>,
+    skeleton: Option<&'a str>,
+    generated: Vec<(&'static str, String)>,
+}
+
+impl<'a> Seed<'a> {
+    pub fn from_matches(matches: &'a ArgMatches<'a>) -> Seed<'a> {
+        Seed {
+            files: matches
+                .values_of("file")
+                .into_iter()
+                .flatten()
+                .filter_map(|file| file.split_once('='))
+                .collect(),
+            skeleton: matches.value_of("skeleton"),
+            generated: Vec::new(),
+        }
+    }
+
+    /// Adds a generated file, which the skeleton and `--file` can replace.
+    pub fn add(&mut self, path: &'static str, contents: String) {
+        self.generated.push((path, contents));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.skeleton.is_none() && self.generated.is_empty()
+    }
+
+    /// Reads every file, with `--file` taking precedence over the skeleton, and the skeleton over
+    /// generated files, for the same path.
+    pub fn read(&self) -> Result<Vec<SeedFile>, Box<dyn Error>> {
+        let mut files = Vec::new();
+        for (path, contents) in &self.generated {
+            files.push(SeedFile {
+                path: path.to_string(),
+                contents: contents.clone().into_bytes(),
+                executable: false,
+            });
+        }
+        if let Some(skeleton) = self.skeleton {
+            let mut skeleton_files = Vec::new();
+            walk(Path::new(skeleton), "", &mut skeleton_files)?;
+            files.retain(|file| skeleton_files.iter().all(|s| s.path != file.path));
+            files.extend(skeleton_files);
+        }
+        for (path, local) in &self.files {
+            let path = path.trim_start_matches('/');
+            files.retain(|file: &SeedFile| file.path != path);
+            files.push(read(Path::new(local), path.to_string())?);
+        }
+        Ok(files)
+    }
+}
+
+fn read(local: &Path, path: String) -> Result<SeedFile, Box<dyn Error>> {
+    let contents =
+        fs::read(local).map_err(|e| format!("Couldn't read `{}`: {}", local.display(), e))?;
+    Ok(SeedFile {
+        path,
+        contents,
+        executable: executable(local),
+    })
+}
+
+#[cfg(unix)]
+fn executable(local: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(local)
+        .map(|metadata| metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn executable(_local: &Path) -> bool {
+    false
+}
+
+/// Adds the files under `dir` in a stable order, skipping `.git`.
+fn walk(dir: &Path, prefix: &str, files: &mut Vec<SeedFile>) -> Result<(), Box<dyn Error>> {
+    let mut entries = fs::read_dir(dir)
+        .map_err(|e| format!("Couldn't read `{}`: {}", dir.display(), e))?
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name == ".git" {
+            continue;
+        }
+        let path = format!("{}{}", prefix, name);
+        if entry.file_type()?.is_dir() {
+            walk(&entry.path(), &format!("{}/", path), files)?;
+        } else {
+            files.push(read(&entry.path(), path)?);
+        }
+    }
+    Ok(())
+}
+
+fn validate_file(value: String) -> Result<(), String> {
+    match value.split_once('=') {
+        Some((path, _)) if path.trim_start_matches('/').is_empty() => {
+            Err("The path in the repo before the `=` is missing.".to_string())
+        }
+        Some((_, local)) if !Path::new(local).is_file() => {
+            Err(format!("`{}` isn't a file.", local))
+        }
+        Some(_) => Ok(()),
+        None => Err("Files are passed as path/in/repo=local/path.".to_string()),
+    }
+}
+
+fn validate_skeleton(dir: String) -> Result<(), String> {
+    if Path::new(&dir).is_dir() {
+        Ok(())
+    } else {
+        Err(format!("`{}` isn't a directory.", dir))
+    }
+}
+
+pub fn args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("file")
+            .long("file")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("path/in/repo=local/path")
+            .validator(validate_file)
+            .help("Commits a local file to the repo after creation, ex `--file .github/CODEOWNERS=templates/CODEOWNERS`."),
+        Arg::with_name("skeleton")
+            .long("skeleton")
+            .takes_value(true)
+            .value_name("dir")
+            .validator(validate_skeleton)
+            .help("Commits every file in a directory to the repo after creation, keeping their paths relative to it. Files passed with --file replace the skeleton's."),
+    ]
+}
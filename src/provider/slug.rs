@@ -0,0 +1,156 @@
+//! Offline checks of repository names against each provider's rules, and the slug each provider will create.
+
+const GITHUB_MAX_LEN: usize = 100;
+const GITLAB_MAX_LEN: usize = 255;
+
+/// Github keeps letters, digits, `.`, `-` and `_`, and replaces every other run of characters with a dash.
+pub fn github(name: &str) -> Result<String, String> {
+    let slug = replace_runs(name, |c| c.is_ascii_alphanumeric() || "._-".contains(c));
+
+    if name.trim().is_empty() {
+        Err("The repository name can't be empty.".to_string())
+    } else if slug == "." || slug == ".." {
+        Err(format!("`{}` is a reserved name on github.", slug))
+    } else if slug.len() > GITHUB_MAX_LEN {
+        Err(format!(
+            "Github names are limited to {} characters, `{}` has {}.",
+            GITHUB_MAX_LEN,
+            slug,
+            slug.len()
+        ))
+    } else {
+        Ok(slug)
+    }
+}
+
+/// Gitlab names may contain letters, digits, emoji, `_`, `.`, `-`, `+` and spaces and must start with a letter, digit, emoji or `_`.
+///
+/// Returns the path gitlab would generate, lowercased with dashes.
+pub fn gitlab_name(name: &str) -> Result<String, String> {
+    match name.chars().next() {
+        None => return Err("The repository name can't be empty.".to_string()),
+        Some(c) if !(c.is_alphanumeric() || c == '_' || is_emoji(c)) => {
+            return Err(format!(
+                "Gitlab names must start with a letter, digit, emoji or `_`, `{}` doesn't.",
+                name
+            ))
+        }
+        _ => {}
+    }
+
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_alphanumeric() || "_.-+ ".contains(*c) || is_emoji(*c)))
+    {
+        return Err(format!(
+            "Gitlab names can only contain letters, digits, emoji, `_`, `.`, `-`, `+` and spaces, `{}` contains `{}`.",
+            name, c
+        ));
+    }
+
+    let path = replace_runs(&name.to_lowercase(), |c| {
+        c.is_ascii_alphanumeric() || "._-".contains(c)
+    });
+    gitlab_path(path.trim_matches(|c| "._-".contains(c)))
+}
+
+/// Gitlab paths may only contain letters, digits, `_`, `-` and `.`, can't start with a special character and can't end in `.git` or `.atom`.
+pub fn gitlab_path(path: &str) -> Result<String, String> {
+    if path.is_empty() {
+        Err("The repository path can't be empty.".to_string())
+    } else if let Some(c) = path
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || "_-.".contains(*c)))
+    {
+        Err(format!(
+            "Gitlab paths can only contain letters, digits, `_`, `-` and `.`, `{}` contains `{}`.",
+            path, c
+        ))
+    } else if path.starts_with(|c: char| "_-.".contains(c)) {
+        Err(format!(
+            "Gitlab paths can't start with a special character, `{}` does.",
+            path
+        ))
+    } else if path.ends_with(".git") || path.ends_with(".atom") {
+        Err(format!(
+            "Gitlab paths can't end in `.git` or `.atom`, `{}` does.",
+            path
+        ))
+    } else if path.len() > GITLAB_MAX_LEN {
+        Err(format!(
+            "Gitlab paths are limited to {} characters, `{}` has {}.",
+            GITLAB_MAX_LEN,
+            path,
+            path.len()
+        ))
+    } else {
+        Ok(path.to_string())
+    }
+}
+
+/// Bitbucket slugs are lowercase letters, digits, `.`, `-` and `_`, with every other run of characters replaced by a dash.
+pub fn bitbucket(name: &str) -> Result<String, String> {
+    let slug = replace_runs(&name.to_lowercase(), |c| {
+        c.is_ascii_alphanumeric() || "._-".contains(c)
+    });
+    let slug = slug.trim_matches('-');
+
+    if slug.is_empty() {
+        Err(format!(
+            "`{}` doesn't contain any characters bitbucket can use in a slug.",
+            name
+        ))
+    } else {
+        Ok(slug.to_string())
+    }
+}
+
+fn replace_runs(name: &str, allowed: impl Fn(char) -> bool) -> String {
+    let mut slug = String::with_capacity(name.len());
+    let mut replacing = false;
+    for c in name.chars() {
+        if allowed(c) {
+            slug.push(c);
+            replacing = false;
+        } else if !replacing {
+            slug.push('-');
+            replacing = true;
+        }
+    }
+    slug
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32, 0x1F300..=0x1FAFF | 0x2600..=0x27BF)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn github_slugs() {
+        assert_eq!(github("Test Repo 123").unwrap(), "Test-Repo-123");
+        assert_eq!(github("a  &  b").unwrap(), "a-b");
+        assert_eq!(github("gitpub.rs").unwrap(), "gitpub.rs");
+        assert!(github("..").is_err());
+        assert!(github(&"a".repeat(101)).is_err());
+    }
+
+    #[test]
+    fn gitlab_names_and_paths() {
+        assert_eq!(gitlab_name("Test Repo 123").unwrap(), "test-repo-123");
+        assert!(gitlab_name("-leading").is_err());
+        assert!(gitlab_name("what?").is_err());
+        assert!(gitlab_path("repo.git").is_err());
+        assert!(gitlab_path("repo.atom").is_err());
+        assert!(gitlab_path(".hidden").is_err());
+        assert_eq!(gitlab_path("my_repo-1.0").unwrap(), "my_repo-1.0");
+    }
+
+    #[test]
+    fn bitbucket_slugs() {
+        assert_eq!(bitbucket("Test Repo 123").unwrap(), "test-repo-123");
+        assert!(bitbucket("!!!").is_err());
+    }
+}
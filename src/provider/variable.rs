@@ -0,0 +1,114 @@
+//! CI secrets and variables provisioned once a repo is created.
+use clap::{Arg, ArgMatches};
+use std::env;
+use std::error::Error;
+use std::fs;
+
+/// A variable parsed from `NAME=value`, or a secret from `NAME=@file` or `NAME=env:VAR`.
+pub struct Variable<'a> {
+    pub name: &'a str,
+    source: &'a str,
+    pub secret: bool,
+}
+
+impl<'a> Variable<'a> {
+    /// The variables passed to `variable`, followed by the secrets passed to `secret`.
+    pub fn from_matches(matches: &'a ArgMatches<'a>) -> Vec<Variable<'a>> {
+        let parse = |arg, secret| {
+            matches
+                .values_of(arg)
+                .into_iter()
+                .flatten()
+                .map(move |value| {
+                    let (name, source) = value
+                        .split_once('=')
+                        .expect("Variables are validated by clap. This should be unreachable.");
+                    Variable {
+                        name,
+                        source,
+                        secret,
+                    }
+                })
+        };
+        parse("variable", false)
+            .chain(parse("secret", true))
+            .collect()
+    }
+
+    /// The value, read from its file or environment variable for secrets.
+    pub fn value(&self) -> Result<String, Box<dyn Error>> {
+        if !self.secret {
+            return Ok(self.source.to_string());
+        }
+        if let Some(path) = self.source.strip_prefix('@') {
+            let value =
+                fs::read_to_string(path).map_err(|e| format!("Couldn't read `{}`: {}", path, e))?;
+            // Files almost always end with a newline that isn't part of the secret.
+            return Ok(value.strip_suffix('\n').unwrap_or(&value).to_string());
+        }
+        let var = self.source.trim_start_matches("env:");
+        env::var(var).map_err(|_| format!("The environment variable `{}` isn't set.", var).into())
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "`{}` isn't a valid name. Use letters, digits and underscores, not starting with a digit.",
+            name
+        ))
+    }
+}
+
+fn validate_variable(value: String) -> Result<(), String> {
+    match value.split_once('=') {
+        Some((name, _)) => validate_name(name),
+        None => Err("Variables are passed as NAME=value.".to_string()),
+    }
+}
+
+fn validate_secret(value: String) -> Result<(), String> {
+    let (name, source) = value
+        .split_once('=')
+        .ok_or_else(|| "Secrets are passed as NAME=@file or NAME=env:VAR.".to_string())?;
+    validate_name(name)?;
+    if let Some(path) = source.strip_prefix('@') {
+        return fs::metadata(path)
+            .map(|_| ())
+            .map_err(|e| format!("Couldn't read `{}`: {}", path, e));
+    }
+    match source.strip_prefix("env:") {
+        Some(var) if env::var(var).is_ok() => Ok(()),
+        Some(var) => Err(format!("The environment variable `{}` isn't set.", var)),
+        // Plain values would end up in the shell history.
+        None => Err(format!(
+            "Pass the secret `{}` as {}=@file or {}=env:VAR.",
+            name, name, name
+        )),
+    }
+}
+
+pub fn variable_arg() -> Arg<'static, 'static> {
+    Arg::with_name("variable")
+        .long("variable")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .value_name("NAME=value")
+        .validator(validate_variable)
+}
+
+pub fn secret_arg() -> Arg<'static, 'static> {
+    Arg::with_name("secret")
+        .long("secret")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .value_name("NAME=@file|NAME=env:VAR")
+        .validator(validate_secret)
+}
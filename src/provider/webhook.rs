@@ -0,0 +1,88 @@
+//! Parses `--webhook` values into a url, github-named events and a secret read from the environment.
+use clap::{Arg, ArgMatches};
+use std::env;
+
+/// A webhook parsed from `url[,events=push,pull_request][,secret_env=VAR]`.
+/// Events use github's names, which each provider maps onto its own.
+pub struct Webhook<'a> {
+    pub url: &'a str,
+    pub events: Vec<&'a str>,
+    pub secret: Option<String>,
+}
+
+/// The url and the `events` and `secret_env` options. Events can be separated
+/// by commas too, so anything without a key belongs to the option before it.
+fn split(value: &str) -> Result<(&str, Vec<&str>, Option<&str>), String> {
+    let mut parts = value.split(',');
+    let url = parts.next().unwrap_or_default();
+    let mut events = vec![];
+    let mut secret_env = None;
+    let mut key = None;
+    for part in parts {
+        let value = match part.split_once('=') {
+            Some((k, value)) => {
+                key = Some(k);
+                value
+            }
+            None => part,
+        };
+        match key {
+            Some("events") => events.push(value),
+            Some("secret_env") if secret_env.is_none() => secret_env = Some(value),
+            Some("secret_env") => return Err("Only one secret_env can be given.".to_string()),
+            Some(other) => {
+                return Err(format!(
+                    "`{}` isn't a webhook option. Use events or secret_env.",
+                    other
+                ))
+            }
+            None => return Err(format!("`{}` should be events=... or secret_env=...", part)),
+        }
+    }
+    events.retain(|event| !event.is_empty());
+    Ok((url, events, secret_env))
+}
+
+impl<'a> Webhook<'a> {
+    fn parse(value: &'a str) -> Webhook<'a> {
+        let (url, mut events, secret_env) =
+            split(value).expect("Webhooks are validated by clap. This should be unreachable.");
+        if events.is_empty() {
+            events.push("push");
+        }
+        Webhook {
+            url,
+            events,
+            secret: secret_env.and_then(|var| env::var(var).ok()),
+        }
+    }
+
+    pub fn from_matches(matches: &'a ArgMatches<'a>) -> Vec<Webhook<'a>> {
+        matches
+            .values_of("webhook")
+            .map(|values| values.map(Webhook::parse).collect())
+            .unwrap_or_default()
+    }
+}
+
+fn validate(value: String) -> Result<(), String> {
+    let (url, _, secret_env) = split(&value)?;
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(format!("`{}` isn't an http or https url.", url));
+    }
+    match secret_env {
+        Some(var) if env::var(var).is_err() => Err(format!("The secret_env `{}` isn't set.", var)),
+        _ => Ok(()),
+    }
+}
+
+pub fn arg() -> Arg<'static, 'static> {
+    Arg::with_name("webhook")
+        .long("webhook")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .value_name("url[,events=...][,secret_env=VAR]")
+        .validator(validate)
+        .help("Registers a webhook after creation, ex `--webhook https://ci.example.com/hook,events=push,pull_request,secret_env=CI_SECRET`. Events use github's names and default to push. The secret is read from the named environment variable.")
+}
@@ -0,0 +1,411 @@
+//! Walks through creating a repo one question at a time, for when the options aren't known by heart.
+use crate::action::shell_quote;
+use crate::cli;
+use crate::git;
+use crate::project::Project;
+use crate::provider::boilerplate;
+use clap::ErrorKind;
+use std::env;
+use std::error::Error;
+use std::io::{self, BufRead, IsTerminal, StdinLock, Write};
+use std::process::exit;
+
+/// The command line to run instead of the real one, built from the answers, when `--interactive` is
+/// passed or required args are missing in a terminal. `None` means the real one is used.
+///
+/// Tokens are asked for when they aren't in the environment, and put there rather than on the
+/// command line.
+pub fn args() -> Option<Vec<String>> {
+    let args = env::args().collect::<Vec<_>>();
+    let interactive = args.iter().skip(1).any(|arg| arg == "--interactive");
+    let terminal = io::stdin().is_terminal() && io::stdout().is_terminal();
+    if interactive && args.len() > 2 {
+        eprintln!(
+            "--interactive asks for every option itself, so it can't be combined with others."
+        );
+        exit(22);
+    }
+
+    let answers = if interactive || (terminal && args.len() == 1) {
+        Wizard::new().run()
+    } else if terminal && missing_required(&args) {
+        Wizard::new().complete(args)
+    } else {
+        return None;
+    };
+    match answers {
+        Ok(Some(args)) => Some(args),
+        Ok(None) => {
+            println!("Nothing was created.");
+            exit(0);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(22);
+        }
+    }
+}
+
+const PROVIDERS: [&str; 3] = ["github", "gitlab", "bitbucket"];
+
+/// The global options that take a value, which can't be mistaken for the subcommand.
+const GLOBAL_VALUES: [&str; 4] = ["--endpoint", "--remote_name", "--pre_hook", "--post_hook"];
+
+/// Checks an answer the way clap checks the arg it's passed as.
+type Validator = fn(String) -> Result<(), String>;
+
+struct Wizard {
+    input: StdinLock<'static>,
+}
+
+impl Wizard {
+    fn new() -> Wizard {
+        Wizard {
+            input: io::stdin().lock(),
+        }
+    }
+
+    /// Asks every question, then shows the command line the answers add up to. `None` means it wasn't confirmed.
+    fn run(&mut self) -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        let project = Project::detect(true);
+        let mut global = vec!["gitpub".to_string()];
+        let mut args = vec![];
+
+        let provider = self.choose("Provider", &PROVIDERS, "github")?;
+        args.push(provider.clone());
+        args.extend(self.credentials(&provider, &[])?);
+
+        let dir = project.name.clone().or_else(|| {
+            env::current_dir()
+                .ok()
+                .and_then(|dir| dir.file_name()?.to_str().map(str::to_string))
+        });
+        let name = loop {
+            let name = self.ask("Name", dir.as_deref())?;
+            if !name.is_empty() {
+                break name;
+            }
+        };
+        args.extend(vec!["-n".to_string(), name]);
+
+        if provider == "gitlab" {
+            let visibility =
+                self.choose("Visibility", &["public", "internal", "private"], "private")?;
+            if visibility != "private" {
+                args.extend(vec!["--visibility".to_string(), visibility]);
+            }
+        } else if self.choose("Visibility", &["public", "private"], "public")? == "private" {
+            args.push("--private".to_string());
+        }
+
+        let description = self.ask("Description", project.description.as_deref())?;
+        if !description.is_empty() && Some(&description) != project.description.as_ref() {
+            args.extend(vec!["-d".to_string(), description]);
+        }
+
+        let license = project
+            .license
+            .as_deref()
+            .and_then(boilerplate::license_key);
+        // Github knows templates gitpub doesn't bundle, so only typos of bundled ones are refused.
+        let github = provider == "github";
+        if let Some(license) = self.ask_optional(
+            "License, like mit or apache-2.0",
+            license,
+            if github {
+                boilerplate::validate_license_typo
+            } else {
+                boilerplate::validate_license
+            },
+        )? {
+            args.extend(vec!["--license".to_string(), license]);
+        }
+        if let Some(gitignore) = self.ask_optional(
+            "Gitignore template, like Rust or Node",
+            None,
+            if github {
+                boilerplate::validate_gitignore_typo
+            } else {
+                boilerplate::validate_gitignore
+            },
+        )? {
+            args.extend(vec!["--gitignore".to_string(), gitignore]);
+        }
+
+        match provider.as_str() {
+            "github" => {
+                let strategies = ["merge", "squash", "rebase"];
+                let allowed = self.choose_many("Merge strategies to allow", &strategies)?;
+                for strategy in strategies
+                    .iter()
+                    .filter(|s| !allowed.contains(&s.to_string()))
+                {
+                    args.push(format!("--disable_{}", strategy));
+                }
+            }
+            "gitlab" => {
+                let method =
+                    self.choose("Merge method", &["merge", "rebase_merge", "ff"], "merge")?;
+                if method != "merge" {
+                    args.extend(vec!["--merge_method".to_string(), method]);
+                }
+            }
+            _ => {}
+        }
+
+        if git::in_repository() && self.confirm("Set it as the `origin` remote?", true)? {
+            global.push("--set_remote".to_string());
+            let exists = git::remotes().iter().any(|(remote, _)| remote == "origin");
+            if exists && self.confirm("`origin` already exists. Replace its url?", false)? {
+                global.push("--replace_remote".to_string());
+            }
+            if self.confirm("Use the ssh url for the remote?", false)? {
+                global.push("--ssh_remote_format".to_string());
+            }
+        }
+
+        global.extend(args);
+        println!();
+        println!(
+            "    {}",
+            global
+                .iter()
+                .map(|arg| shell_quote(arg))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        println!();
+        if self.confirm("Create it?", true)? {
+            Ok(Some(global))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Asks only for the credentials the command line's provider is missing.
+    fn complete(&mut self, mut args: Vec<String>) -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        if let Some(provider) = subcommand(&args).filter(|arg| PROVIDERS.contains(arg)) {
+            let provider = provider.to_string();
+            let credentials = self.credentials(&provider, &args)?;
+            args.extend(credentials);
+        }
+        Ok(Some(args))
+    }
+
+    /// Asks for the token and the bitbucket username unless they're in the environment or `args`.
+    /// The token is put into the environment, so only the username is returned as args.
+    fn credentials(
+        &mut self,
+        provider: &str,
+        args: &[String],
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let passed = |flags: &[&str]| {
+            args.iter().any(|arg| {
+                flags
+                    .iter()
+                    .any(|flag| arg == flag || arg.starts_with(&format!("{}=", flag)))
+            })
+        };
+        let token = format!("{}_REPO_TOKEN", provider.to_uppercase());
+        if env::var_os(&token).is_none() && !passed(&["-t", "--token"]) {
+            let answer = loop {
+                let answer = self.ask_secret(&format!("{} token", provider))?;
+                if !answer.is_empty() {
+                    break answer;
+                }
+            };
+            env::set_var(&token, answer);
+        }
+
+        let mut credentials = vec![];
+        if provider == "bitbucket"
+            && env::var_os("BITBUCKET_USERNAME").is_none()
+            && !passed(&["--username"])
+        {
+            let username = loop {
+                let username = self.ask("Bitbucket username", None)?;
+                if !username.is_empty() {
+                    break username;
+                }
+            };
+            credentials.extend(vec!["--username".to_string(), username]);
+        }
+        Ok(credentials)
+    }
+
+    /// Asks without echoing the answer when stdin is a terminal.
+    fn ask_secret(&mut self, question: &str) -> Result<String, Box<dyn Error>> {
+        let hidden = io::stdin().is_terminal() && echo(false);
+        let answer = self.ask(question, None);
+        if hidden {
+            echo(true);
+            println!();
+        }
+        answer
+    }
+
+    /// Asks a question and returns the trimmed answer, or the default when the answer is empty.
+    fn ask(&mut self, question: &str, default: Option<&str>) -> Result<String, Box<dyn Error>> {
+        match default {
+            Some(default) => print!("{} [{}]: ", question, default),
+            None => print!("{}: ", question),
+        }
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        if self.input.read_line(&mut answer)? == 0 {
+            println!();
+            return Err(
+                "The input ended before every question was answered, so nothing was created."
+                    .into(),
+            );
+        }
+        let answer = answer.trim();
+        Ok(match (answer.is_empty(), default) {
+            (true, Some(default)) => default.to_string(),
+            _ => answer.to_string(),
+        })
+    }
+
+    /// Asks until the answer is one of the options, ignoring case.
+    fn choose(
+        &mut self,
+        question: &str,
+        options: &[&str],
+        default: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        let question = format!("{} ({})", question, options.join(", "));
+        loop {
+            let answer = self.ask(&question, Some(default))?.to_lowercase();
+            if options.contains(&answer.as_str()) {
+                return Ok(answer);
+            }
+            println!("Choose one of: {}.", options.join(", "));
+        }
+    }
+
+    /// Asks for a comma separated list of the options, defaulting to all of them.
+    fn choose_many(
+        &mut self,
+        question: &str,
+        options: &[&str],
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let all = options.join(",");
+        loop {
+            let answer = self.ask(question, Some(&all))?.to_lowercase();
+            let chosen = answer
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|choice| !choice.is_empty())
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+            if !chosen.is_empty()
+                && chosen
+                    .iter()
+                    .all(|choice| options.contains(&choice.as_str()))
+            {
+                return Ok(chosen);
+            }
+            println!("Choose at least one of: {}.", options.join(", "));
+        }
+    }
+
+    /// Asks for something that can be left out with `none`, or by leaving it empty without a default.
+    fn ask_optional(
+        &mut self,
+        question: &str,
+        default: Option<&str>,
+        validate: Validator,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        let question = match default {
+            Some(_) => format!("{}, or none", question),
+            None => format!("{} (empty for none)", question),
+        };
+        loop {
+            let answer = self.ask(&question, default)?;
+            if answer.is_empty() || answer.eq_ignore_ascii_case("none") {
+                return Ok(None);
+            }
+            match validate(answer.clone()) {
+                Ok(()) => return Ok(Some(answer)),
+                Err(e) => println!("{}", e),
+            }
+        }
+    }
+
+    fn confirm(&mut self, question: &str, default: bool) -> Result<bool, Box<dyn Error>> {
+        let hint = if default { "Y/n" } else { "y/N" };
+        loop {
+            match self
+                .ask(&format!("{} [{}]", question, hint), None)?
+                .to_lowercase()
+                .as_str()
+            {
+                "" => return Ok(default),
+                "y" | "yes" => return Ok(true),
+                "n" | "no" => return Ok(false),
+                _ => println!("Answer yes or no."),
+            }
+        }
+    }
+}
+
+/// The subcommand, which is the first arg that isn't a global option or its value.
+fn subcommand(args: &[String]) -> Option<&str> {
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        if GLOBAL_VALUES.contains(&arg.as_str()) {
+            args.next();
+        } else if !arg.starts_with('-') {
+            return Some(arg);
+        }
+    }
+    None
+}
+
+/// Whether parsing `args` fails only because a required arg is missing, like the token.
+fn missing_required(args: &[String]) -> bool {
+    match cli::get_app().get_matches_from_safe(args) {
+        Err(e) => e.kind == ErrorKind::MissingRequiredArgument,
+        Ok(_) => false,
+    }
+}
+
+/// Turns the terminal's echo on or off. Returns whether it worked.
+#[cfg(unix)]
+fn echo(on: bool) -> bool {
+    std::process::Command::new("stty")
+        .arg(if on { "echo" } else { "-echo" })
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(not(unix))]
+fn echo(_on: bool) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn subcommand_is_the_first_positional_arg() {
+        let command = args(&["gitpub", "gitlab", "-n", "github"]);
+        assert_eq!(subcommand(&command), Some("gitlab"));
+        let command = args(&[
+            "gitpub",
+            "--remote_name",
+            "github",
+            "bitbucket",
+            "-n",
+            "repo",
+        ]);
+        assert_eq!(subcommand(&command), Some("bitbucket"));
+        let command = args(&["gitpub", "--set_remote", "--endpoint=https://x", "github"]);
+        assert_eq!(subcommand(&command), Some("github"));
+        assert_eq!(subcommand(&args(&["gitpub", "--set_remote"])), None);
+    }
+}
@@ -0,0 +1,328 @@
+mod forge;
+
+use forge::{route, stderr, stdout, Checkout, Forge};
+use serde_json::json;
+
+#[test]
+fn create_uses_basic_auth_and_slug() {
+    let forge = Forge::start(|url| forge::bitbucket(url, "test-repo"));
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &[
+            "--set_remote",
+            "--ssh_remote_format",
+            "bitbucket",
+            "-n",
+            "Test Repo",
+            "--fork_policy",
+            "no_forks",
+            "--language",
+            "rust",
+        ],
+        &[
+            ("BITBUCKET_USERNAME", "atlas"),
+            ("BITBUCKET_REPO_TOKEN", "app-password"),
+            ("BITBUCKET_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+
+    let create = &forge.requests_to("POST", "/repositories/atlas/test-repo")[0];
+    // base64("atlas:app-password")
+    assert_eq!(
+        create.header("authorization"),
+        Some("Basic YXRsYXM6YXBwLXBhc3N3b3Jk")
+    );
+    let payload = create.json();
+    assert_eq!(payload["name"], "Test Repo");
+    assert_eq!(payload["fork_policy"], "no_forks");
+    assert_eq!(payload["language"], "rust");
+    assert!(payload.get("username").is_none());
+    assert!(payload.get("token").is_none());
+
+    assert_eq!(
+        checkout.git(&["remote", "get-url", "origin"]),
+        "git@bitbucket.org:atlas/test-repo.git"
+    );
+}
+
+#[test]
+fn list_follows_next_links_in_a_workspace() {
+    let forge = Forge::start(|url| {
+        vec![
+            route(
+                "GET",
+                "/repositories/team?page=2",
+                200,
+                json!({ "values": [{ "full_name": "team/two", "is_private": false }] }),
+            ),
+            route(
+                "GET",
+                "/repositories/team",
+                200,
+                json!({
+                    "values": [{ "full_name": "team/one", "is_private": true }],
+                    "next": format!("{}/repositories/team?page=2", url),
+                }),
+            ),
+        ]
+    });
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &["bitbucket", "list", "--workspace", "team"],
+        &[
+            ("BITBUCKET_USERNAME", "atlas"),
+            ("BITBUCKET_REPO_TOKEN", "app-password"),
+            ("BITBUCKET_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let table = stdout(&out);
+    assert!(table.contains("team/one  private"));
+    assert!(table.contains("team/two  public"));
+}
+
+#[test]
+fn protect_adds_branch_restrictions() {
+    let forge = Forge::start(|url| {
+        let mut routes = vec![route(
+            "POST",
+            "/repositories/atlas/repo/branch-restrictions",
+            201,
+            json!({ "id": 1 }),
+        )];
+        routes.extend(forge::bitbucket(url, "repo"));
+        routes
+    });
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &[
+            "bitbucket",
+            "-n",
+            "repo",
+            "--protect",
+            "main",
+            "--required_reviews",
+            "2",
+        ],
+        &[
+            ("BITBUCKET_USERNAME", "atlas"),
+            ("BITBUCKET_REPO_TOKEN", "app-password"),
+            ("BITBUCKET_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let kinds = forge
+        .requests_to("POST", "/repositories/atlas/repo/branch-restrictions")
+        .iter()
+        .map(|r| r.json()["kind"].as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        ["force", "delete", "push", "require_approvals_to_merge"]
+    );
+}
+
+#[test]
+fn collaborators_are_resolved_to_account_ids() {
+    let forge = Forge::start(|url| {
+        let mut routes = vec![
+            route(
+                "GET",
+                "/workspaces/atlas/members?pagelen=100",
+                200,
+                json!({ "values": [{ "user": { "nickname": "ada", "account_id": "557058:ada" } }] }),
+            ),
+            route(
+                "PUT",
+                "/repositories/atlas/repo/permissions-config/users/557058:ada",
+                200,
+                json!({ "permission": "admin" }),
+            ),
+            route(
+                "PUT",
+                "/repositories/atlas/repo/permissions-config/groups/developers",
+                200,
+                json!({ "permission": "write" }),
+            ),
+        ];
+        routes.extend(forge::bitbucket(url, "repo"));
+        routes
+    });
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &[
+            "bitbucket",
+            "-n",
+            "repo",
+            "--collaborator",
+            "ada:admin",
+            "--team",
+            "developers",
+        ],
+        &[
+            ("BITBUCKET_USERNAME", "atlas"),
+            ("BITBUCKET_REPO_TOKEN", "app-password"),
+            ("BITBUCKET_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        forge.requests_to(
+            "PUT",
+            "/repositories/atlas/repo/permissions-config/users/557058:ada"
+        )[0]
+        .json(),
+        json!({ "permission": "admin" })
+    );
+    assert_eq!(
+        forge.requests_to(
+            "PUT",
+            "/repositories/atlas/repo/permissions-config/groups/developers"
+        )[0]
+        .json(),
+        json!({ "permission": "write" })
+    );
+}
+
+#[test]
+fn webhook_events_map_to_bitbucket_events() {
+    let forge = Forge::start(|url| {
+        let mut routes = vec![route(
+            "POST",
+            "/repositories/atlas/repo/hooks",
+            201,
+            json!({ "uuid": "{1}" }),
+        )];
+        routes.extend(forge::bitbucket(url, "repo"));
+        routes
+    });
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &[
+            "bitbucket",
+            "-n",
+            "repo",
+            "--webhook",
+            "https://ci.example.com/hook,events=push,repo:fork",
+        ],
+        &[
+            ("BITBUCKET_USERNAME", "atlas"),
+            ("BITBUCKET_REPO_TOKEN", "app-password"),
+            ("BITBUCKET_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let hook = forge.requests_to("POST", "/repositories/atlas/repo/hooks")[0].json();
+    assert_eq!(hook["url"], "https://ci.example.com/hook");
+    assert_eq!(hook["events"], json!(["repo:push", "repo:fork"]));
+    assert_eq!(hook["active"], true);
+}
+
+#[test]
+fn seed_files_are_uploaded_as_a_form() {
+    let forge = Forge::start(|url| {
+        let mut routes = vec![route(
+            "POST",
+            "/repositories/atlas/repo/src",
+            201,
+            json!(null),
+        )];
+        routes.extend(forge::bitbucket(url, "repo"));
+        routes
+    });
+    let checkout = Checkout::new();
+    std::fs::write(checkout.dir().join("pipelines.yml"), "pipelines: {}\n").unwrap();
+
+    let out = checkout.gitpub(
+        &[
+            "bitbucket",
+            "-n",
+            "repo",
+            "--file",
+            "bitbucket-pipelines.yml=pipelines.yml",
+        ],
+        &[
+            ("BITBUCKET_USERNAME", "atlas"),
+            ("BITBUCKET_REPO_TOKEN", "app-password"),
+            ("BITBUCKET_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let upload = &forge.requests_to("POST", "/repositories/atlas/repo/src")[0];
+    assert!(upload
+        .header("content-type")
+        .unwrap()
+        .starts_with("multipart/form-data"));
+    assert!(upload
+        .body
+        .contains("name=\"message\"\r\n\r\nAdd initial files"));
+    assert!(upload
+        .body
+        .contains("name=\"bitbucket-pipelines.yml\"; filename=\"bitbucket-pipelines.yml\""));
+    assert!(upload.body.contains("pipelines: {}\n"));
+}
+
+#[test]
+fn topics_are_rejected_with_a_reason() {
+    let forge = Forge::start(|url| forge::bitbucket(url, "repo"));
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &["bitbucket", "-n", "repo", "--topic", "rust"],
+        &[
+            ("BITBUCKET_REPO_TOKEN", "secret"),
+            ("BITBUCKET_USERNAME", "atlas"),
+            ("BITBUCKET_API_URL", forge.url()),
+        ],
+    );
+    assert!(!out.status.success());
+    assert!(stderr(&out).contains("Bitbucket repos have no topics."));
+    assert!(forge.requests().is_empty());
+}
+
+#[test]
+fn protection_options_are_checked_before_creating() {
+    let forge = Forge::start(|url| forge::bitbucket(url, "repo"));
+    let checkout = Checkout::new();
+    let envs = [
+        ("BITBUCKET_REPO_TOKEN", "secret"),
+        ("BITBUCKET_USERNAME", "atlas"),
+        ("BITBUCKET_API_URL", forge.url()),
+    ];
+
+    let out = checkout.gitpub(
+        &[
+            "bitbucket",
+            "-n",
+            "repo",
+            "--protect",
+            "main",
+            "--required_reviews",
+            "two",
+        ],
+        &envs,
+    );
+    assert!(!out.status.success());
+    assert!(stderr(&out).contains("`two` isn't a number of reviews"));
+
+    let out = checkout.gitpub(
+        &[
+            "bitbucket",
+            "-n",
+            "repo",
+            "--protect",
+            "main",
+            "--enforce_admins",
+        ],
+        &envs,
+    );
+    assert!(!out.status.success());
+    assert!(stderr(&out).contains("--enforce_admins"));
+    assert!(forge.requests().is_empty());
+}
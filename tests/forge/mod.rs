@@ -0,0 +1,332 @@
+//! An in-process fake forge that records requests and answers them with canned responses,
+//! and a scratch git checkout to run gitpub in.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde_json::{json, Value};
+
+/// A request received by the forge.
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    /// The path including any query string.
+    pub path: String,
+    /// Header names are lowercased.
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).expect("The request body wasn't JSON.")
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+}
+
+/// A canned response for a method and path. The query string is ignored when matching unless the route has one.
+pub struct Route {
+    method: &'static str,
+    path: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Value,
+}
+
+impl Route {
+    pub fn header(mut self, name: &str, value: &str) -> Route {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn matches(&self, request: &Request) -> bool {
+        let path = if self.path.contains('?') {
+            request.path.as_str()
+        } else {
+            request.path.split('?').next().unwrap_or_default()
+        };
+        self.method == request.method && self.path == path
+    }
+}
+
+pub fn route(method: &'static str, path: &str, status: u16, body: Value) -> Route {
+    Route {
+        method,
+        path: path.to_string(),
+        status,
+        headers: Vec::new(),
+        body,
+    }
+}
+
+pub struct Forge {
+    url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl Forge {
+    /// Starts a forge on a free local port. Routes are built from the forge's url and the first matching route answers.
+    ///
+    /// Requests without a matching route get a 404.
+    pub fn start(routes: impl FnOnce(&str) -> Vec<Route>) -> Forge {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let routes = Arc::new(routes(&url));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+                let routes = routes.clone();
+                let recorded = recorded.clone();
+                thread::spawn(move || serve(stream, &routes, &recorded));
+            }
+        });
+
+        Forge { url, requests }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The requests made with a method to a path, ignoring query strings.
+    pub fn requests_to(&self, method: &str, path: &str) -> Vec<Request> {
+        self.requests()
+            .into_iter()
+            .filter(|r| r.method == method && r.path.split('?').next() == Some(path))
+            .collect()
+    }
+}
+
+fn serve(stream: TcpStream, routes: &[Route], recorded: &Mutex<Vec<Request>>) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut line = String::new();
+    if reader.read_line(&mut line).is_err() || line.is_empty() {
+        return;
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).unwrap();
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let length = headers
+        .get("content-length")
+        .and_then(|l| l.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+
+    let request = Request {
+        method,
+        path,
+        headers,
+        body: String::from_utf8(body).unwrap(),
+    };
+
+    let (status, extra, body) = match routes.iter().find(|r| r.matches(&request)) {
+        Some(route) => (route.status, route.headers.clone(), route.body.to_string()),
+        None => (
+            404,
+            Vec::new(),
+            json!({ "message": "Not Found" }).to_string(),
+        ),
+    };
+    recorded.lock().unwrap().push(request);
+
+    let mut response = format!(
+        "HTTP/1.1 {} Canned\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        body.len()
+    );
+    for (name, value) in extra {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str("\r\n");
+    response.push_str(&body);
+
+    let mut stream = stream;
+    let _ = stream.write_all(response.as_bytes());
+}
+
+/// Github's responses for the user `octocat` creating `name`.
+pub fn github(url: &str, name: &str) -> Vec<Route> {
+    let repo = json!({
+        "id": 1296269,
+        "name": name,
+        "full_name": format!("octocat/{}", name),
+        "private": false,
+        "html_url": format!("{}/octocat/{}", url, name),
+        "clone_url": format!("{}/octocat/{}.git", url, name),
+        "ssh_url": format!("git@github.com:octocat/{}.git", name),
+        "default_branch": "master",
+    });
+    vec![
+        route("GET", "/user", 200, json!({ "login": "octocat", "id": 1 })),
+        route("POST", "/user/repos", 201, repo)
+            .header("Location", &format!("{}/repos/octocat/{}", url, name)),
+    ]
+}
+
+/// Gitlab's responses for the user `tanuki` creating `path`.
+pub fn gitlab(url: &str, path: &str) -> Vec<Route> {
+    let project = json!({
+        "id": 3,
+        "name": path,
+        "path": path,
+        "path_with_namespace": format!("tanuki/{}", path),
+        "web_url": format!("{}/tanuki/{}", url, path),
+        "http_url_to_repo": format!("{}/tanuki/{}.git", url, path),
+        "ssh_url_to_repo": format!("git@gitlab.com:tanuki/{}.git", path),
+        "visibility": "private",
+    });
+    vec![
+        route(
+            "GET",
+            "/user",
+            200,
+            json!({ "username": "tanuki", "id": 1 }),
+        ),
+        route("POST", "/projects", 201, project),
+    ]
+}
+
+/// Bitbucket's responses for the user `atlas` creating `slug`.
+pub fn bitbucket(url: &str, slug: &str) -> Vec<Route> {
+    let repo = json!({
+        "slug": slug,
+        "full_name": format!("atlas/{}", slug),
+        "is_private": false,
+        "links": {
+            "html": { "href": format!("{}/atlas/{}", url, slug) },
+            "clone": [
+                { "name": "https", "href": format!("{}/atlas/{}.git", url, slug) },
+                { "name": "ssh", "href": format!("git@bitbucket.org:atlas/{}.git", slug) },
+            ],
+        },
+    });
+    vec![route(
+        "POST",
+        &format!("/repositories/atlas/{}", slug),
+        200,
+        repo,
+    )]
+}
+
+/// A scratch git repository that's removed when dropped.
+pub struct Checkout {
+    dir: PathBuf,
+}
+
+impl Checkout {
+    pub fn new() -> Checkout {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let dir = env::temp_dir().join(format!(
+            "gitpub-test-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let checkout = Checkout { dir };
+        checkout.git(&["init", "-q", "."]);
+        checkout
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Runs gitpub in the checkout with no provider credentials or api urls inherited from the environment.
+    pub fn gitpub(&self, args: &[&str], envs: &[(&str, &str)]) -> Output {
+        self.command(args, envs).output().unwrap()
+    }
+
+    /// Runs gitpub like `gitpub`, writing `input` to its stdin.
+    pub fn gitpub_with_stdin(&self, args: &[&str], envs: &[(&str, &str)], input: &str) -> Output {
+        let mut child = self
+            .command(args, envs)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap()
+    }
+
+    fn command(&self, args: &[&str], envs: &[(&str, &str)]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_gitpub-bin"));
+        command.current_dir(&self.dir).args(args);
+        for (name, _) in env::vars() {
+            if name.starts_with("GITHUB_")
+                || name.starts_with("GITLAB_")
+                || name.starts_with("BITBUCKET_")
+                || name.starts_with("GITPUB_")
+            {
+                command.env_remove(name);
+            }
+        }
+        command.envs(envs.iter().cloned());
+        command
+    }
+
+    pub fn git(&self, args: &[&str]) -> String {
+        let out = Command::new("git")
+            .current_dir(&self.dir)
+            .args(args)
+            .output()
+            .unwrap();
+        String::from_utf8(out.stdout).unwrap().trim().to_string()
+    }
+}
+
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+pub fn stdout(out: &Output) -> String {
+    String::from_utf8_lossy(&out.stdout).to_string()
+}
+
+pub fn stderr(out: &Output) -> String {
+    String::from_utf8_lossy(&out.stderr).to_string()
+}
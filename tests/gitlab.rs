@@ -0,0 +1,876 @@
+mod forge;
+
+use forge::{route, stderr, stdout, Checkout, Forge};
+use serde_json::json;
+
+#[test]
+fn create_sends_payload_and_sets_remote() {
+    let forge = Forge::start(|url| forge::gitlab(url, "test-repo"));
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &[
+            "--set_remote",
+            "gitlab",
+            "-n",
+            "Test Repo",
+            "--visibility",
+            "private",
+            "--disable_jobs",
+            "--merge_method",
+            "ff",
+            "--tag_list",
+            "rust",
+            "cli",
+        ],
+        &[
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+
+    let create = &forge.requests_to("POST", "/projects")[0];
+    assert_eq!(create.header("private-token"), Some("secret"));
+    let payload = create.json();
+    assert_eq!(payload["name"], "Test Repo");
+    assert_eq!(payload["path"], "test-repo");
+    assert_eq!(payload["visibility"], "private");
+    assert_eq!(payload["jobs_enabled"], false);
+    assert_eq!(payload["merge_method"], "ff");
+    assert_eq!(payload["topics"], json!(["rust", "cli"]));
+
+    assert_eq!(
+        checkout.git(&["remote", "get-url", "origin"]),
+        format!("{}/tanuki/test-repo.git", forge.url())
+    );
+}
+
+#[test]
+fn merge_requests_stay_enabled_and_mirror_builds_are_sent() {
+    let forge = Forge::start(|url| forge::gitlab(url, "repo"));
+    let checkout = Checkout::new();
+    let envs = [
+        ("GITLAB_REPO_TOKEN", "secret"),
+        ("GITLAB_API_URL", forge.url()),
+    ];
+
+    let out = checkout.gitpub(
+        &[
+            "gitlab",
+            "-n",
+            "repo",
+            "--mirror",
+            "--mirror_triggers_builds",
+        ],
+        &envs,
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let payload = forge.requests_to("POST", "/projects")[0].json();
+    assert_eq!(payload["merge_requests_enabled"], true);
+    assert_eq!(payload["mirror_trigger_builds"], true);
+
+    let out = checkout.gitpub(&["gitlab", "-n", "repo", "--disable_merge"], &envs);
+    assert!(out.status.success(), "{}", stderr(&out));
+    let payload = forge.requests_to("POST", "/projects")[1].json();
+    assert_eq!(payload["merge_requests_enabled"], false);
+}
+
+#[test]
+fn existing_project_in_namespace() {
+    let forge = Forge::start(|url| {
+        let mut routes = vec![
+            route(
+                "GET",
+                "/namespaces/42",
+                200,
+                json!({ "full_path": "group/sub" }),
+            ),
+            route(
+                "GET",
+                "/projects/group%2Fsub%2Frepo",
+                200,
+                json!({ "web_url": format!("{}/group/sub/repo", url) }),
+            ),
+        ];
+        routes.extend(forge::gitlab(url, "repo"));
+        routes
+    });
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &["gitlab", "--path", "repo", "--namespace_id", "42"],
+        &[
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", forge.url()),
+        ],
+    );
+    assert_eq!(out.status.code(), Some(17));
+    assert!(forge.requests_to("POST", "/projects").is_empty());
+}
+
+#[test]
+fn invalid_path_is_rejected_offline() {
+    let forge = Forge::start(|url| forge::gitlab(url, "repo"));
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &["gitlab", "--path", "repo.git"],
+        &[
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", forge.url()),
+        ],
+    );
+    assert_eq!(out.status.code(), Some(22));
+    assert!(forge.requests().is_empty());
+}
+
+#[test]
+fn list_follows_next_page_header() {
+    let forge = Forge::start(|_| {
+        vec![
+            route(
+                "GET",
+                "/groups/group%2Fsub/projects?include_subgroups=true&per_page=100&page=2",
+                200,
+                json!([{ "path_with_namespace": "group/sub/two", "visibility": "public", "archived": false }]),
+            ),
+            route(
+                "GET",
+                "/groups/group%2Fsub/projects",
+                200,
+                json!([{ "path_with_namespace": "group/sub/one", "visibility": "private", "archived": true }]),
+            )
+            .header("X-Next-Page", "2"),
+        ]
+    });
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &["gitlab", "list", "--group", "group/sub", "--json"],
+        &[
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let repos: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(repos[0]["name"], "group/sub/one");
+    assert_eq!(repos[0]["private"], true);
+    assert_eq!(repos[1]["name"], "group/sub/two");
+}
+
+#[test]
+fn show_command_recreates_the_same_payload() {
+    let forge = Forge::start(|_| {
+        vec![route(
+            "GET",
+            "/projects/tanuki%2Fgolden",
+            200,
+            json!({
+                "name": "golden",
+                "path": "golden",
+                "path_with_namespace": "tanuki/golden",
+                "namespace": { "id": 1, "kind": "user" },
+                "visibility": "internal",
+                "default_branch": "main",
+                "issues_enabled": true,
+                "merge_requests_enabled": false,
+                "only_allow_merge_if_pipeline_succeeds": true,
+                "mirror_trigger_builds": true,
+                "topics": ["rust", "cli"],
+            }),
+        )]
+    });
+    let checkout = Checkout::new();
+    let envs = [
+        ("GITLAB_REPO_TOKEN", "secret"),
+        ("GITLAB_API_URL", forge.url()),
+    ];
+
+    let out = checkout.gitpub(&["gitlab", "show", "tanuki/golden", "--command"], &envs);
+    assert!(out.status.success(), "{}", stderr(&out));
+    let command = String::from_utf8(out.stdout).unwrap();
+    assert_eq!(
+        command.trim(),
+        "gitpub gitlab --name golden --visibility internal --default_branch main --disable_merge --pipeline_success_required --tag_list rust cli --mirror_triggers_builds"
+    );
+
+    let fresh = Forge::start(|url| forge::gitlab(url, "golden"));
+    let args = command.split_whitespace().skip(1).collect::<Vec<_>>();
+    let out = checkout.gitpub(
+        &args,
+        &[
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", fresh.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let payload = fresh.requests_to("POST", "/projects")[0].json();
+    assert_eq!(payload["visibility"], "internal");
+    assert_eq!(payload["merge_requests_enabled"], false);
+    assert_eq!(payload["issues_enabled"], true);
+    assert_eq!(payload["only_allow_merge_if_pipeline_succeeds"], true);
+    assert_eq!(payload["mirror_trigger_builds"], true);
+    assert_eq!(payload["topics"], json!(["rust", "cli"]));
+}
+
+#[test]
+fn custom_templates_are_checked_before_creating() {
+    let forge = Forge::start(|url| {
+        let mut routes = vec![
+            route(
+                "GET",
+                "/groups/7/projects",
+                200,
+                json!([{ "id": 70, "name": "Service Scaffold", "path": "service-scaffold" }]),
+            ),
+            route("GET", "/projects/70", 200, json!({ "id": 70 })),
+        ];
+        routes.extend(forge::gitlab(url, "billing"));
+        routes
+    });
+    let checkout = Checkout::new();
+    let envs = [
+        ("GITLAB_REPO_TOKEN", "secret"),
+        ("GITLAB_API_URL", forge.url()),
+    ];
+
+    let out = checkout.gitpub(
+        &[
+            "gitlab",
+            "-n",
+            "billing",
+            "--template_name",
+            "service-scaffold",
+            "--group_with_project_templates_id",
+            "7",
+        ],
+        &envs,
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let payload = forge.requests_to("POST", "/projects")[0].json();
+    assert_eq!(payload["template_name"], "service-scaffold");
+    assert_eq!(payload["use_custom_template"], true);
+    assert_eq!(payload["group_with_project_templates_id"], 7);
+
+    let out = checkout.gitpub(
+        &[
+            "gitlab",
+            "-n",
+            "billing",
+            "--template_name",
+            "missing",
+            "--group_with_project_templates_id",
+            "7",
+        ],
+        &envs,
+    );
+    assert_eq!(out.status.code(), Some(22));
+    assert!(stderr(&out).contains("There's no custom template named `missing`."));
+
+    let out = checkout.gitpub(
+        &[
+            "gitlab",
+            "-n",
+            "billing",
+            "--template_name",
+            "a&b c",
+            "--use_custom_template",
+        ],
+        &envs,
+    );
+    assert_eq!(out.status.code(), Some(22));
+    let search = forge.requests_to("GET", "/projects").pop().unwrap();
+    assert_eq!(search.path, "/projects?simple=true&search=a%26b%20c");
+
+    let out = checkout.gitpub(
+        &["gitlab", "-n", "billing", "--template_project_id", "71"],
+        &envs,
+    );
+    assert_eq!(out.status.code(), Some(22));
+    assert_eq!(forge.requests_to("POST", "/projects").len(), 1);
+}
+
+#[test]
+fn unknown_built_in_template_only_warns() {
+    let forge = Forge::start(|url| forge::gitlab(url, "site"));
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &["gitlab", "-n", "site", "--template_name", "newframework"],
+        &[
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(stderr(&out).contains("`newframework` isn't one of the built-in templates"));
+    let payload = forge.requests_to("POST", "/projects")[0].json();
+    assert_eq!(payload["template_name"], "newframework");
+    assert_eq!(payload["use_custom_template"], false);
+}
+
+#[test]
+fn fork_into_a_namespace_waits_for_the_import() {
+    let forge = Forge::start(|url| {
+        vec![
+            route(
+                "GET",
+                "/projects/gitlab-org%2Fgitlab",
+                200,
+                json!({ "id": 278964, "http_url_to_repo": format!("{}/gitlab-org/gitlab.git", url) }),
+            ),
+            route(
+                "POST",
+                "/projects/gitlab-org%2Fgitlab/fork",
+                201,
+                json!({
+                    "id": 9,
+                    "web_url": format!("{}/team/gitlab", url),
+                    "http_url_to_repo": format!("{}/team/gitlab.git", url),
+                    "import_status": "scheduled",
+                }),
+            ),
+            route(
+                "GET",
+                "/projects/9",
+                200,
+                json!({ "id": 9, "import_status": "finished" }),
+            ),
+        ]
+    });
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &[
+            "--set_remote",
+            "gitlab",
+            "fork",
+            "gitlab-org/gitlab",
+            "--namespace_id",
+            "12",
+        ],
+        &[
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        forge.requests_to("POST", "/projects/gitlab-org%2Fgitlab/fork")[0].json(),
+        json!({ "namespace_id": 12 })
+    );
+    assert_eq!(forge.requests_to("GET", "/projects/9").len(), 1);
+    assert_eq!(
+        checkout.git(&["remote", "get-url", "origin"]),
+        format!("{}/team/gitlab.git", forge.url())
+    );
+    assert_eq!(
+        checkout.git(&["remote", "get-url", "upstream"]),
+        format!("{}/gitlab-org/gitlab.git", forge.url())
+    );
+}
+
+#[test]
+fn archive_reads_repos_from_stdin() {
+    let forge = Forge::start(|_| {
+        vec![
+            route(
+                "POST",
+                "/projects/team%2Fold-api/archive",
+                201,
+                json!({ "archived": true }),
+            ),
+            route(
+                "POST",
+                "/projects/team%2Fold-web/archive",
+                201,
+                json!({ "archived": true }),
+            ),
+        ]
+    });
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub_with_stdin(
+        &["gitlab", "archive"],
+        &[
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", forge.url()),
+        ],
+        "team/old-api\n\n# Still in use\nteam/missing\nteam/old-web\n",
+    );
+    assert_eq!(out.status.code(), Some(2));
+    let archived = stdout(&out);
+    assert!(archived.contains("Archived team/old-api"));
+    assert!(archived.contains("Archived team/old-web"));
+    assert!(stderr(&out).contains("Couldn't update team/missing"));
+    assert!(stderr(&out).contains("1 of 3 repos couldn't be updated."));
+}
+
+#[test]
+fn transfer_to_a_namespace_id_uses_the_reported_path() {
+    let forge = Forge::start(|url| {
+        vec![
+            route(
+                "GET",
+                "/projects/tanuki%2Fapi",
+                200,
+                json!({ "http_url_to_repo": format!("{}/tanuki/api.git", url) }),
+            ),
+            route(
+                "PUT",
+                "/projects/tanuki%2Fapi/transfer",
+                200,
+                json!({
+                    "path_with_namespace": "platform/backend/api",
+                    "web_url": format!("{}/platform/backend/api", url),
+                    "http_url_to_repo": format!("{}/platform/backend/api.git", url),
+                    "ssh_url_to_repo": "git@gitlab.com:platform/backend/api.git",
+                }),
+            ),
+        ]
+    });
+    let checkout = Checkout::new();
+    checkout.git(&[
+        "remote",
+        "add",
+        "origin",
+        &format!("{}/tanuki/api.git", forge.url()),
+    ]);
+
+    let out = checkout.gitpub(
+        &["gitlab", "transfer", "tanuki/api", "--to", "42"],
+        &[
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        forge.requests_to("PUT", "/projects/tanuki%2Fapi/transfer")[0].json(),
+        json!({ "namespace": "42" })
+    );
+    assert_eq!(
+        checkout.git(&["remote", "get-url", "origin"]),
+        format!("{}/platform/backend/api.git", forge.url())
+    );
+}
+
+#[test]
+fn protect_maps_to_protected_branches_and_project_settings() {
+    let forge = Forge::start(|url| {
+        let mut routes = vec![route(
+            "POST",
+            "/projects/tanuki%2Frepo/protected_branches",
+            201,
+            json!({ "name": "main" }),
+        )];
+        routes.extend(forge::gitlab(url, "repo"));
+        routes
+    });
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &[
+            "gitlab",
+            "-n",
+            "repo",
+            "--protect",
+            "main",
+            "--required_reviews",
+            "1",
+            "--required_status_checks",
+            "build",
+            "--linear_history",
+        ],
+        &[
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let payload = forge.requests_to("POST", "/projects")[0].json();
+    assert_eq!(payload["approvals_before_merge"], 1);
+    assert_eq!(payload["only_allow_merge_if_pipeline_succeeds"], true);
+    assert_eq!(payload["merge_method"], "ff");
+    assert_eq!(
+        forge.requests_to("POST", "/projects/tanuki%2Frepo/protected_branches")[0].json(),
+        json!({ "name": "main", "push_access_level": 0, "merge_access_level": 40, "allow_force_push": false })
+    );
+}
+
+#[test]
+fn members_and_groups_are_looked_up_by_name() {
+    let forge = Forge::start(|url| {
+        let mut routes = vec![
+            route(
+                "GET",
+                "/users?username=octocat",
+                200,
+                json!([{ "id": 7, "username": "octocat" }]),
+            ),
+            route("GET", "/users?username=ghost", 200, json!([])),
+            route(
+                "GET",
+                "/groups/platform%2Fbackend",
+                200,
+                json!({ "id": 12 }),
+            ),
+            route(
+                "POST",
+                "/projects/tanuki%2Frepo/members",
+                201,
+                json!({ "id": 7 }),
+            ),
+            route(
+                "POST",
+                "/projects/tanuki%2Frepo/share",
+                201,
+                json!({ "id": 1 }),
+            ),
+        ];
+        routes.extend(forge::gitlab(url, "repo"));
+        routes
+    });
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &[
+            "gitlab",
+            "-n",
+            "repo",
+            "--collaborator",
+            "octocat:maintainer",
+            "--collaborator",
+            "ghost",
+            "--team",
+            "platform/backend:read",
+        ],
+        &[
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", forge.url()),
+        ],
+    );
+    // The missing user fails its step, but the rest still run.
+    assert_eq!(out.status.code(), Some(2));
+    assert!(stderr(&out).contains("There's no gitlab user named `ghost`."));
+    let members = forge.requests_to("POST", "/projects/tanuki%2Frepo/members");
+    assert_eq!(members.len(), 1);
+    assert_eq!(
+        members[0].json(),
+        json!({ "user_id": 7, "access_level": 40 })
+    );
+    assert_eq!(
+        forge.requests_to("POST", "/projects/tanuki%2Frepo/share")[0].json(),
+        json!({ "group_id": 12, "group_access": 20 })
+    );
+}
+
+#[test]
+fn webhook_events_map_to_gitlab_flags() {
+    let forge = Forge::start(|url| {
+        let mut routes = vec![route(
+            "POST",
+            "/projects/tanuki%2Frepo/hooks",
+            201,
+            json!({ "id": 1 }),
+        )];
+        routes.extend(forge::gitlab(url, "repo"));
+        routes
+    });
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &[
+            "gitlab",
+            "-n",
+            "repo",
+            "--webhook",
+            "https://ci.example.com/hook,events=pull_request,pipeline,secret_env=CI_SECRET",
+        ],
+        &[
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", forge.url()),
+            ("CI_SECRET", "hunter2"),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        forge.requests_to("POST", "/projects/tanuki%2Frepo/hooks")[0].json(),
+        json!({
+            "url": "https://ci.example.com/hook",
+            "push_events": false,
+            "merge_requests_events": true,
+            "pipeline_events": true,
+            "token": "hunter2",
+        })
+    );
+}
+
+#[test]
+fn existing_deploy_key_is_uploaded_read_only() {
+    let forge = Forge::start(|url| {
+        let mut routes = vec![route(
+            "POST",
+            "/projects/tanuki%2Frepo/deploy_keys",
+            201,
+            json!({ "id": 1 }),
+        )];
+        routes.extend(forge::gitlab(url, "repo"));
+        routes
+    });
+    let checkout = Checkout::new();
+    let key =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl server";
+    std::fs::write(checkout.dir().join("server.pub"), format!("{}\n", key)).unwrap();
+
+    let out = checkout.gitpub(
+        &["gitlab", "-n", "repo", "--deploy_public_key", "server.pub"],
+        &[
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        forge.requests_to("POST", "/projects/tanuki%2Frepo/deploy_keys")[0].json(),
+        json!({ "title": "server", "key": key, "can_push": false })
+    );
+}
+
+#[test]
+fn variables_and_secrets_become_ci_variables() {
+    let forge = Forge::start(|url| {
+        let mut routes = vec![route(
+            "POST",
+            "/projects/tanuki%2Frepo/variables",
+            201,
+            json!({}),
+        )];
+        routes.extend(forge::gitlab(url, "repo"));
+        routes
+    });
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &[
+            "gitlab",
+            "-n",
+            "repo",
+            "--variable",
+            "REGISTRY=registry.gitlab.com",
+            "--secret",
+            "REGISTRY_TOKEN=env:CI_REGISTRY_TOKEN",
+            "--protected_variables",
+        ],
+        &[
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", forge.url()),
+            ("CI_REGISTRY_TOKEN", "glpat-0123456789"),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let variables = forge
+        .requests_to("POST", "/projects/tanuki%2Frepo/variables")
+        .iter()
+        .map(|r| r.json())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        variables,
+        [
+            json!({ "key": "REGISTRY", "value": "registry.gitlab.com", "protected": true, "masked": false }),
+            json!({ "key": "REGISTRY_TOKEN", "value": "glpat-0123456789", "protected": true, "masked": true }),
+        ]
+    );
+}
+
+#[test]
+fn labels_are_created_with_hash_colors() {
+    let forge = Forge::start(|url| {
+        let mut routes = vec![route(
+            "POST",
+            "/projects/tanuki%2Frepo/labels",
+            201,
+            json!({}),
+        )];
+        routes.extend(forge::gitlab(url, "repo"));
+        routes
+    });
+    let checkout = Checkout::new();
+    std::fs::write(
+        checkout.dir().join("labels.toml"),
+        "[[label]]\nname = \"needs triage\"\ncolor = \"ededed\"\n",
+    )
+    .unwrap();
+
+    let out = checkout.gitpub(
+        &["gitlab", "-n", "repo", "--labels", "labels.toml"],
+        &[
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        forge
+            .requests_to("GET", "/projects/tanuki%2Frepo/labels/needs%20triage")
+            .len(),
+        1
+    );
+    assert_eq!(
+        forge.requests_to("POST", "/projects/tanuki%2Frepo/labels")[0].json(),
+        json!({ "name": "needs triage", "color": "#ededed", "description": "" })
+    );
+
+    std::fs::write(
+        checkout.dir().join("labels.toml"),
+        "[[label]]\nname = \"x\"\ncolor = \"red\"\n",
+    )
+    .unwrap();
+    let out = checkout.gitpub(
+        &["gitlab", "-n", "repo", "--labels", "labels.toml"],
+        &[("GITLAB_REPO_TOKEN", "secret")],
+    );
+    assert!(!out.status.success());
+    assert!(stderr(&out).contains("The color of `x` should be six hex digits"));
+}
+
+#[test]
+fn seed_files_are_committed_in_one_commit() {
+    let forge = Forge::start(|url| {
+        let mut routes = vec![
+            route(
+                "GET",
+                "/projects/tanuki%2Frepo/repository/files/README.md",
+                200,
+                json!({ "file_path": "README.md" }),
+            ),
+            route(
+                "POST",
+                "/projects/tanuki%2Frepo/repository/commits",
+                201,
+                json!({ "id": "c1" }),
+            ),
+        ];
+        routes.extend(forge::gitlab(url, "repo"));
+        routes
+    });
+    let checkout = Checkout::new();
+    std::fs::write(checkout.dir().join("README.md"), "# Repo\n").unwrap();
+    std::fs::write(checkout.dir().join("ci.yml"), "test: {}\n").unwrap();
+
+    let out = checkout.gitpub(
+        &[
+            "gitlab",
+            "-n",
+            "repo",
+            "--initialize_with_readme",
+            "--default_branch",
+            "trunk",
+            "--file",
+            "README.md=README.md",
+            "--file",
+            ".gitlab-ci.yml=ci.yml",
+        ],
+        &[
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        forge.requests_to("GET", "/projects/tanuki%2Frepo/repository/files/README.md")[0].path,
+        "/projects/tanuki%2Frepo/repository/files/README.md?ref=trunk"
+    );
+    let commit = forge.requests_to("POST", "/projects/tanuki%2Frepo/repository/commits")[0].json();
+    assert_eq!(commit["branch"], "trunk");
+    assert_eq!(commit["commit_message"], "Add initial files");
+    assert_eq!(
+        commit["actions"],
+        json!([
+            {
+                "action": "update",
+                "file_path": "README.md",
+                "content": base64::encode("# Repo\n"),
+                "encoding": "base64",
+                "execute_filemode": false,
+            },
+            {
+                "action": "create",
+                "file_path": ".gitlab-ci.yml",
+                "content": base64::encode("test: {}\n"),
+                "encoding": "base64",
+                "execute_filemode": false,
+            },
+        ])
+    );
+}
+
+#[test]
+fn license_and_gitignore_are_committed() {
+    let forge = Forge::start(|url| {
+        let mut routes = vec![route(
+            "POST",
+            "/projects/tanuki%2Frepo/repository/commits",
+            201,
+            json!({ "id": "c1" }),
+        )];
+        routes.extend(forge::gitlab(url, "repo"));
+        routes
+    });
+    let checkout = Checkout::new();
+    checkout.git(&["config", "user.name", "Tanuki"]);
+
+    let out = checkout.gitpub(
+        &[
+            "gitlab",
+            "-n",
+            "repo",
+            "--license",
+            "BSD-3-Clause",
+            "--gitignore",
+            "node",
+        ],
+        &[
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let commit = forge.requests_to("POST", "/projects/tanuki%2Frepo/repository/commits")[0].json();
+    let actions = commit["actions"].as_array().unwrap();
+    assert_eq!(actions[0]["file_path"], "LICENSE");
+    assert_eq!(actions[1]["file_path"], ".gitignore");
+    let license = base64::decode(actions[0]["content"].as_str().unwrap()).unwrap();
+    let license = String::from_utf8(license).unwrap();
+    assert!(license.contains("Tanuki"));
+    assert!(license.contains("Redistribution and use in source and binary forms"));
+    assert!(!checkout.dir().join("LICENSE").exists());
+}
+
+#[test]
+fn gitignore_gitpub_doesnt_bundle_fails_before_any_request() {
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &["gitlab", "-n", "repo", "--gitignore", "Rsut"],
+        &[("GITLAB_REPO_TOKEN", "secret")],
+    );
+    assert!(!out.status.success());
+    assert!(stderr(&out).contains("Did you mean `Rust`?"));
+}
+
+#[test]
+fn template_ids_that_arent_numbers_fail_before_any_request() {
+    let checkout = Checkout::new();
+
+    for arg in ["--template_project_id", "--group_with_project_templates_id"] {
+        let out = checkout.gitpub(
+            &["gitlab", "-n", "repo", arg, "foo"],
+            &[("GITLAB_REPO_TOKEN", "secret")],
+        );
+        assert!(!out.status.success());
+        assert!(stderr(&out).contains("`foo` isn't an id, like 42."));
+    }
+}
@@ -0,0 +1,83 @@
+mod forge;
+
+use forge::{stderr, Checkout, Forge};
+use std::fs;
+
+#[test]
+fn failing_pre_hook_stops_the_repo_being_created() {
+    let forge = Forge::start(|url| forge::github(url, "repo"));
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &[
+            "--pre_hook",
+            "test \"$GITPUB_NAME\" != repo || { echo 'Names need a team prefix.' >&2; exit 1; }",
+            "github",
+            "-n",
+            "repo",
+        ],
+        &[
+            ("GITHUB_REPO_TOKEN", "secret"),
+            ("GITHUB_API_URL", forge.url()),
+        ],
+    );
+    assert_eq!(out.status.code(), Some(22));
+    assert!(stderr(&out).contains("Names need a team prefix."));
+    assert!(stderr(&out).contains("so nothing was created"));
+    assert!(forge.requests_to("POST", "/user/repos").is_empty());
+}
+
+#[test]
+fn hooks_describe_the_repo() {
+    let forge = Forge::start(|url| forge::github(url, "repo"));
+    let checkout = Checkout::new();
+    checkout.git(&[
+        "config",
+        "gitpub.preHook",
+        "echo \"$GITPUB_PROVIDER $GITPUB_NAME $GITPUB_REMOTE\" > pre",
+    ]);
+
+    let out = checkout.gitpub(
+        &[
+            "--set_remote",
+            "--post_hook",
+            "cat > repo.json; echo \"$GITPUB_WEB_URL $GITPUB_SSH_URL\" > post; git remote get-url origin >> post",
+            "github",
+            "-n",
+            "repo",
+        ],
+        &[
+            ("GITHUB_REPO_TOKEN", "secret"),
+            ("GITHUB_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let read = |name| fs::read_to_string(checkout.dir().join(name)).unwrap();
+    assert_eq!(read("pre"), "github repo origin\n");
+    assert_eq!(
+        read("post"),
+        format!(
+            "{0}/octocat/repo git@github.com:octocat/repo.git\n{0}/octocat/repo.git\n",
+            forge.url()
+        )
+    );
+    let repo: serde_json::Value = serde_json::from_str(&read("repo.json")).unwrap();
+    assert_eq!(repo["full_name"], "octocat/repo");
+}
+
+#[test]
+fn failing_post_hook_fails_the_run() {
+    let forge = Forge::start(|url| forge::github(url, "repo"));
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &["--post_hook", "exit 3", "github", "-n", "repo"],
+        &[
+            ("GITHUB_REPO_TOKEN", "secret"),
+            ("GITHUB_API_URL", forge.url()),
+        ],
+    );
+    assert_eq!(out.status.code(), Some(2));
+    assert!(stderr(&out).contains("The post-hook `exit 3` failed."));
+    assert_eq!(forge.requests_to("POST", "/user/repos").len(), 1);
+}
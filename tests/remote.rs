@@ -0,0 +1,170 @@
+mod forge;
+
+use forge::{route, stderr, Checkout, Forge};
+use serde_json::json;
+
+#[test]
+fn existing_remote_is_checked_before_creating() {
+    let forge = Forge::start(|url| forge::github(url, "repo"));
+    let checkout = Checkout::new();
+    checkout.git(&["remote", "add", "origin", "https://example.com/old.git"]);
+    let envs = [
+        ("GITHUB_REPO_TOKEN", "secret"),
+        ("GITHUB_API_URL", forge.url()),
+    ];
+
+    let out = checkout.gitpub(&["--set_remote", "github", "-n", "repo"], &envs);
+    assert_eq!(out.status.code(), Some(17));
+    assert!(forge.requests().is_empty());
+
+    let out = checkout.gitpub(
+        &["--set_remote", "--replace_remote", "github", "-n", "repo"],
+        &envs,
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        checkout.git(&["remote", "get-url", "origin"]),
+        format!("{}/octocat/repo.git", forge.url())
+    );
+}
+
+#[test]
+fn add_push_url_keeps_the_original() {
+    let forge = Forge::start(|url| forge::github(url, "repo"));
+    let checkout = Checkout::new();
+    checkout.git(&["remote", "add", "origin", "https://example.com/old.git"]);
+
+    let out = checkout.gitpub(
+        &["--set_remote", "--add_push_url", "github", "-n", "repo"],
+        &[
+            ("GITHUB_REPO_TOKEN", "secret"),
+            ("GITHUB_API_URL", forge.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        checkout.git(&["remote", "get-url", "--push", "--all", "origin"]),
+        format!(
+            "https://example.com/old.git\n{}/octocat/repo.git",
+            forge.url()
+        )
+    );
+}
+
+#[test]
+fn multi_mirrors_through_push_urls() {
+    let github = Forge::start(|url| forge::github(url, "repo"));
+    let gitlab = Forge::start(|url| forge::gitlab(url, "repo"));
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &[
+            "--set_remote",
+            "multi",
+            "-n",
+            "repo",
+            "-p",
+            "--providers",
+            "github",
+            "gitlab",
+        ],
+        &[
+            ("GITHUB_REPO_TOKEN", "secret"),
+            ("GITHUB_API_URL", github.url()),
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", gitlab.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        github.requests_to("POST", "/user/repos")[0].json()["private"],
+        true
+    );
+    assert_eq!(
+        gitlab.requests_to("POST", "/projects")[0].json()["visibility"],
+        "private"
+    );
+
+    let github_url = format!("{}/octocat/repo.git", github.url());
+    let gitlab_url = format!("{}/tanuki/repo.git", gitlab.url());
+    assert_eq!(checkout.git(&["remote", "get-url", "origin"]), github_url);
+    assert_eq!(
+        checkout.git(&["remote", "get-url", "--push", "--all", "origin"]),
+        format!("{}\n{}", github_url, gitlab_url)
+    );
+}
+
+#[test]
+fn multi_remote_per_provider() {
+    let github = Forge::start(|url| forge::github(url, "repo"));
+    let gitlab = Forge::start(|url| forge::gitlab(url, "repo"));
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &[
+            "--set_remote",
+            "multi",
+            "-n",
+            "repo",
+            "--providers",
+            "github",
+            "gitlab",
+            "--remote_per_provider",
+        ],
+        &[
+            ("GITHUB_REPO_TOKEN", "secret"),
+            ("GITHUB_API_URL", github.url()),
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", gitlab.url()),
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        checkout.git(&["remote", "get-url", "github"]),
+        format!("{}/octocat/repo.git", github.url())
+    );
+    assert_eq!(
+        checkout.git(&["remote", "get-url", "gitlab"]),
+        format!("{}/tanuki/repo.git", gitlab.url())
+    );
+}
+
+#[test]
+fn multi_lists_the_repos_created_before_a_provider_fails() {
+    let github = Forge::start(|url| forge::github(url, "repo"));
+    let gitlab = Forge::start(|url| {
+        let mut routes = vec![route(
+            "POST",
+            "/projects",
+            401,
+            json!({ "message": "401 Unauthorized" }),
+        )];
+        routes.extend(forge::gitlab(url, "repo"));
+        routes
+    });
+    let checkout = Checkout::new();
+
+    let out = checkout.gitpub(
+        &[
+            "--set_remote",
+            "multi",
+            "-n",
+            "repo",
+            "--providers",
+            "github",
+            "gitlab",
+        ],
+        &[
+            ("GITHUB_REPO_TOKEN", "secret"),
+            ("GITHUB_API_URL", github.url()),
+            ("GITLAB_REPO_TOKEN", "secret"),
+            ("GITLAB_API_URL", gitlab.url()),
+        ],
+    );
+    assert_eq!(out.status.code(), Some(3));
+    assert!(stderr(&out).contains("You are unauthorized to create that repo."));
+    assert!(stderr(&out).contains(&format!(
+        "These repos were created before the failure:\n    {}/octocat/repo\n",
+        github.url()
+    )));
+}
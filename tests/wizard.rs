@@ -0,0 +1,114 @@
+mod forge;
+
+use forge::{stderr, stdout, Checkout, Forge};
+
+#[test]
+fn wizard_builds_the_command_line() {
+    let forge = Forge::start(|url| forge::github(url, "repo"));
+    let checkout = Checkout::new();
+
+    let answers = [
+        "",        // github
+        "repo",    // name
+        "private", // visibility
+        "It's a test",
+        "MTI", // a typo, asked again
+        "mit",
+        "rust",
+        "sideways", // not a strategy, asked again
+        "squash",
+        "y", // set the remote
+        "",  // https
+        "",  // create it
+    ];
+    let out = checkout.gitpub_with_stdin(
+        &["--interactive"],
+        &[
+            ("GITHUB_REPO_TOKEN", "secret"),
+            ("GITHUB_API_URL", forge.url()),
+        ],
+        &(answers.join("\n") + "\n"),
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(stdout(&out).contains("Did you mean `mit`?"));
+    assert!(stdout(&out).contains("Choose at least one of: merge, squash, rebase."));
+    assert!(stdout(&out).contains(
+        "gitpub --set_remote github -n repo --private -d 'It'\\''s a test' --license mit --gitignore rust --disable_merge --disable_rebase"
+    ));
+
+    let payload = forge.requests_to("POST", "/user/repos")[0].json();
+    assert_eq!(payload["private"], true);
+    assert_eq!(payload["description"], "It's a test");
+    assert_eq!(payload["license_template"], "mit");
+    assert_eq!(payload["gitignore_template"], "Rust");
+    assert_eq!(payload["allow_squash_merge"], true);
+    assert_eq!(payload["allow_merge_commit"], false);
+    assert_eq!(payload["allow_rebase_merge"], false);
+    assert_eq!(
+        checkout.git(&["remote", "get-url", "origin"]),
+        format!("{}/octocat/repo.git", forge.url())
+    );
+}
+
+#[test]
+fn wizard_creates_nothing_unless_confirmed() {
+    let forge = Forge::start(|url| forge::gitlab(url, "repo"));
+    let checkout = Checkout::new();
+    let envs = [
+        ("GITLAB_REPO_TOKEN", "secret"),
+        ("GITLAB_API_URL", forge.url()),
+    ];
+
+    let answers = ["gitlab", "repo", "internal", "", "", "", "ff", "n", "n"];
+    let out = checkout.gitpub_with_stdin(&["--interactive"], &envs, &(answers.join("\n") + "\n"));
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(
+        stdout(&out).contains("gitpub gitlab -n repo --visibility internal --merge_method ff\n")
+    );
+    assert!(stdout(&out).contains("Nothing was created."));
+    assert!(forge.requests().is_empty());
+
+    let out = checkout.gitpub_with_stdin(&["--interactive"], &envs, "gitlab\nrepo\n");
+    assert_eq!(out.status.code(), Some(22));
+    assert!(stderr(&out).contains("The input ended before every question was answered"));
+    assert!(forge.requests().is_empty());
+
+    let out = checkout.gitpub(&["--interactive", "gitlab", "-n", "repo"], &envs);
+    assert_eq!(out.status.code(), Some(22));
+    assert!(forge.requests().is_empty());
+}
+
+#[test]
+fn wizard_asks_for_credentials_missing_from_the_environment() {
+    let forge = Forge::start(|url| forge::bitbucket(url, "repo"));
+    let checkout = Checkout::new();
+
+    let answers = [
+        "bitbucket",
+        "app-password", // token
+        "atlas",        // username
+        "repo",
+        "",
+        "",
+        "",
+        "",
+        "n", // don't set the remote
+        "",  // create it
+    ];
+    let out = checkout.gitpub_with_stdin(
+        &["--interactive"],
+        &[("BITBUCKET_API_URL", forge.url())],
+        &(answers.join("\n") + "\n"),
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(stdout(&out).contains("bitbucket token: "));
+    assert!(stdout(&out).contains("gitpub bitbucket --username atlas -n repo\n"));
+    assert!(!stdout(&out).contains("app-password\n"));
+
+    let create = &forge.requests_to("POST", "/repositories/atlas/repo")[0];
+    // base64("atlas:app-password")
+    assert_eq!(
+        create.header("authorization"),
+        Some("Basic YXRsYXM6YXBwLXBhc3N3b3Jk")
+    );
+}